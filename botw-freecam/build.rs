@@ -1,6 +1,12 @@
 use winres;
 
 fn main() {
+    // The interceptor and the resources are only needed by the dll, the rest of the crate can be
+    // built and tested on other platforms.
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("windows") {
+        return;
+    }
+
     let res = winres::WindowsResource::new();
 
     println!("cargo:rerun-if-changed=interceptor.asm");
@@ -0,0 +1,165 @@
+#[cfg(windows)]
+use crate::utils::check_key_press;
+#[cfg(test)]
+use std::collections::VecDeque;
+#[cfg(windows)]
+use winapi::um::xinput;
+
+/// Virtual-key codes used by the bindings. They're the same as the ones in the Windows API, but
+/// don't depend on it so the bindings can be used on any platform.
+pub mod vk {
+    pub const BACK: i32 = 0x08;
+    pub const TAB: i32 = 0x09;
+    pub const RETURN: i32 = 0x0D;
+    pub const SHIFT: i32 = 0x10;
+    pub const CONTROL: i32 = 0x11;
+    pub const MENU: i32 = 0x12;
+    pub const SPACE: i32 = 0x20;
+    pub const PRIOR: i32 = 0x21;
+    pub const NEXT: i32 = 0x22;
+    pub const END: i32 = 0x23;
+    pub const HOME: i32 = 0x24;
+    pub const LEFT: i32 = 0x25;
+    pub const UP: i32 = 0x26;
+    pub const RIGHT: i32 = 0x27;
+    pub const DOWN: i32 = 0x28;
+    pub const INSERT: i32 = 0x2D;
+    pub const DELETE: i32 = 0x2E;
+    pub const NUMPAD0: i32 = 0x60;
+    pub const F1: i32 = 0x70;
+    pub const LSHIFT: i32 = 0xA0;
+    pub const RSHIFT: i32 = 0xA1;
+    pub const LCONTROL: i32 = 0xA2;
+    pub const RCONTROL: i32 = 0xA3;
+    pub const LMENU: i32 = 0xA4;
+    pub const RMENU: i32 = 0xA5;
+    pub const OEM_1: i32 = 0xBA;
+    pub const OEM_PLUS: i32 = 0xBB;
+    pub const OEM_COMMA: i32 = 0xBC;
+    pub const OEM_MINUS: i32 = 0xBD;
+    pub const OEM_PERIOD: i32 = 0xBE;
+    pub const OEM_2: i32 = 0xBF;
+    pub const OEM_3: i32 = 0xC0;
+    pub const OEM_4: i32 = 0xDB;
+    pub const OEM_5: i32 = 0xDC;
+    pub const OEM_6: i32 = 0xDD;
+    pub const OEM_7: i32 = 0xDE;
+}
+
+/// Bits of `Gamepad::buttons`, the same as XInput's.
+pub mod buttons {
+    pub const DPAD_UP: u16 = 0x0001;
+    pub const DPAD_DOWN: u16 = 0x0002;
+    pub const DPAD_LEFT: u16 = 0x0004;
+    pub const DPAD_RIGHT: u16 = 0x0008;
+    pub const START: u16 = 0x0010;
+    pub const BACK: u16 = 0x0020;
+    pub const LEFT_THUMB: u16 = 0x0040;
+    pub const RIGHT_THUMB: u16 = 0x0080;
+    pub const LEFT_SHOULDER: u16 = 0x0100;
+    pub const RIGHT_SHOULDER: u16 = 0x0200;
+    pub const A: u16 = 0x1000;
+    pub const B: u16 = 0x2000;
+    pub const X: u16 = 0x4000;
+    pub const Y: u16 = 0x8000;
+}
+
+/// State of a controller, independent of XInput.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct Gamepad {
+    pub buttons: u16,
+    pub left_trigger: u8,
+    pub right_trigger: u8,
+    pub left_stick: (i16, i16),
+    pub right_stick: (i16, i16),
+}
+
+/// Where the keyboard and controller state comes from. The mapping from this state to `Input`
+/// doesn't know anything about the platform, so it can be driven by something else than the
+/// real devices.
+pub trait InputSource {
+    /// Whether the virtual key `key` is being held.
+    fn key_down(&self, key: i32) -> bool;
+
+    /// State of the first controller. A disconnected controller reports everything released.
+    fn gamepad(&self) -> Gamepad;
+}
+
+/// Reads the real keyboard and the first XInput controller.
+#[cfg(windows)]
+pub struct WindowsInput;
+
+#[cfg(windows)]
+impl InputSource for WindowsInput {
+    fn key_down(&self, key: i32) -> bool {
+        check_key_press(key)
+    }
+
+    fn gamepad(&self) -> Gamepad {
+        let mut xs: xinput::XINPUT_STATE = unsafe { std::mem::zeroed() };
+        if unsafe { xinput::XInputGetState(0, &mut xs) } != 0 {
+            return Gamepad::default();
+        }
+
+        let gp = xs.Gamepad;
+        Gamepad {
+            buttons: gp.wButtons,
+            left_trigger: gp.bLeftTrigger,
+            right_trigger: gp.bRightTrigger,
+            left_stick: (gp.sThumbLX, gp.sThumbLY),
+            right_stick: (gp.sThumbRX, gp.sThumbRY),
+        }
+    }
+}
+
+/// One tick of scripted input.
+#[cfg(test)]
+#[derive(Default, Debug, Clone)]
+pub struct Frame {
+    pub keys: Vec<i32>,
+    pub gamepad: Gamepad,
+}
+
+/// Replays a list of frames, one per tick, so the input handling can run without the real
+/// devices. Once the frames run out everything stays released.
+#[cfg(test)]
+#[derive(Default, Debug)]
+pub struct ScriptedInput {
+    frames: VecDeque<Frame>,
+    current: Frame,
+}
+
+#[cfg(test)]
+impl ScriptedInput {
+    pub fn new(frames: Vec<Frame>) -> Self {
+        let mut frames: VecDeque<Frame> = frames.into();
+        let current = frames.pop_front().unwrap_or_default();
+
+        Self { frames, current }
+    }
+
+    /// Moves to the next frame. Returns false when there are no frames left.
+    pub fn advance(&mut self) -> bool {
+        match self.frames.pop_front() {
+            Some(frame) => {
+                self.current = frame;
+                true
+            }
+            None => {
+                self.current = Frame::default();
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl InputSource for ScriptedInput {
+    fn key_down(&self, key: i32) -> bool {
+        self.current.keys.contains(&key)
+    }
+
+    fn gamepad(&self) -> Gamepad {
+        self.current.gamepad
+    }
+}
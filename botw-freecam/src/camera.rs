@@ -1,5 +1,15 @@
 use crate::utils::*;
 use nalgebra_glm as glm;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Distance from the camera to its focus while flying, which also scales the movement.
+const FOCUS_DISTANCE: f32 = 5.;
+
+/// Closest the camera gets to the target while orbiting.
+const ORBIT_MIN_RADIUS: f32 = 1.;
+
+/// Keeps the camera from going right above or below the target, where the view flips.
+const ORBIT_MIN_ELEVATION: f32 = 0.05;
 
 #[derive(Clone, Copy)]
 pub struct Vec3BE(pub [FloatBE; 3]);
@@ -57,7 +67,33 @@ impl From<FloatBE> for f32 {
 
 
 impl GameCamera {
+    /// Moves the camera with the speeds of `input` during `input.dt` seconds.
     pub fn consume_input(&mut self, input: &Input) {
+        self.fly(input, input.delta_focus);
+    }
+
+    /// Moves the camera like `consume_input`, but keeps it looking at `target`, so the look
+    /// controls are ignored.
+    pub fn consume_look_at_input(&mut self, input: &Input, target: &glm::Vec3) {
+        self.fly(input, (0., 0.));
+        self.look_at(target, input.roll);
+    }
+
+    /// Points the camera to `target`, rotated `roll` radians around the view direction.
+    pub fn look_at(&mut self, target: &glm::Vec3, roll: f32) {
+        let pos = glm::Vec3::from(self.pos);
+
+        // There's no direction to look at when the camera is right on the target.
+        if glm::distance(&pos, target) < 1e-3 {
+            return;
+        }
+
+        self.focus = (*target).into();
+        self.rot = GameCamera::calculate_rotation(*target, pos, roll).into();
+    }
+
+    fn fly(&mut self, input: &Input, delta_focus: (f32, f32)) {
+        let dt = input.dt;
         let r_cam_x = f32::from(self.focus.0[0]) - f32::from(self.pos.0[0]);
         let r_cam_y = f32::from(self.focus.0[1]) - f32::from(self.pos.0[1]);
         let r_cam_z = f32::from(self.focus.0[2]) - f32::from(self.pos.0[2]);
@@ -66,20 +102,21 @@ impl GameCamera {
             r_cam_x,
             r_cam_z,
             r_cam_y,
-            input.delta_focus.0,
-            input.delta_focus.1,
+            delta_focus.0 * dt,
+            delta_focus.1 * dt,
+            input.max_pitch,
         );
 
+        let delta_pos = (input.delta_pos.0 * dt, input.delta_pos.1 * dt);
+        let delta_altitude = input.delta_altitude * dt;
+
         self.pos.0[0] =
-            (f32::from(self.pos.0[0]) + r_cam_x * input.delta_pos.1 + input.delta_pos.0 * r_cam_z)
-                .into();
+            (f32::from(self.pos.0[0]) + r_cam_x * delta_pos.1 + delta_pos.0 * r_cam_z).into();
 
-        self.pos.0[1] =
-            (f32::from(self.pos.0[1]) + r_cam_y * input.delta_pos.1 + input.delta_altitude).into();
+        self.pos.0[1] = (f32::from(self.pos.0[1]) + r_cam_y * delta_pos.1 + delta_altitude).into();
 
-        self.pos.0[2] = (f32::from(self.pos.0[2]) + r_cam_z * input.delta_pos.1
-            - input.delta_pos.0 * r_cam_x)
-            .into();
+        self.pos.0[2] =
+            (f32::from(self.pos.0[2]) + r_cam_z * delta_pos.1 - delta_pos.0 * r_cam_x).into();
 
         self.focus.0[0] = (f32::from(self.pos.0[0]) + r_cam_x).into();
         self.focus.0[1] = (f32::from(self.pos.0[1]) + r_cam_y).into();
@@ -87,25 +124,64 @@ impl GameCamera {
 
         let pos_ = glm::Vec3::from(self.pos);
         let focus_ = glm::Vec3::from(self.focus);
-        let result = GameCamera::calculate_rotation(focus_, pos_, input.delta_rotation);
+        let result = GameCamera::calculate_rotation(focus_, pos_, input.roll);
         self.rot = result.into();
 
         self.fov = input.fov.into();
     }
 
+    /// Moves the camera around `target`, which stays in the center of the view. Strafing and
+    /// looking change the azimuth, the height changes the elevation and moving forward or
+    /// backward changes the distance to the target.
+    pub fn consume_orbit_input(&mut self, input: &Input, target: &glm::Vec3) {
+        let dt = input.dt;
+        let offset = glm::Vec3::from(self.pos) - target;
+        let radius = glm::length(&offset).max(ORBIT_MIN_RADIUS);
+
+        // Same spherical coordinates as `calc_new_focus_point`, but around the target. The
+        // speeds are divided by the radius so the camera moves as fast as when flying.
+        let delta_pos = (
+            input.delta_pos.0 * FOCUS_DISTANCE,
+            input.delta_pos.1 * FOCUS_DISTANCE,
+        );
+        let azimuth = offset[2].atan2(offset[0]);
+        let elevation = offset.xz().norm().atan2(offset[1]);
+
+        let theta = azimuth + (delta_pos.0 / radius + input.delta_focus.0) * dt;
+        let phi = elevation - (input.delta_altitude / radius + input.delta_focus.1) * dt;
+        let phi = phi.clamp(ORBIT_MIN_ELEVATION, PI - ORBIT_MIN_ELEVATION);
+        let radius = (radius - delta_pos.1 * dt).max(ORBIT_MIN_RADIUS);
+
+        let pos = target
+            + glm::vec3(
+                radius * theta.cos() * phi.sin(),
+                radius * phi.cos(),
+                radius * theta.sin() * phi.sin(),
+            );
+
+        self.pos = pos.into();
+        self.focus = (*target).into();
+        self.rot = GameCamera::calculate_rotation(*target, pos, input.roll).into();
+        self.fov = input.fov.into();
+    }
+
     pub fn calc_new_focus_point(
         cam_x: f32,
         cam_z: f32,
         cam_y: f32,
         speed_x: f32,
         speed_y: f32,
+        max_pitch: f32,
     ) -> (f32, f32, f32) {
         // use spherical coordinates to add speed
         let theta = cam_z.atan2(cam_x) + speed_x;
 
         let phi = (cam_x.powi(2) + cam_z.powi(2)).sqrt().atan2(cam_y) + speed_y;
 
-        let r = 5.;
+        // phi goes from 0 (straight up) to pi (straight down), past them the view flips.
+        let phi = phi.clamp(FRAC_PI_2 - max_pitch, FRAC_PI_2 + max_pitch);
+
+        let r = FOCUS_DISTANCE;
 
         let r_cam_x = r * theta.cos() * phi.sin();
         let r_cam_z = r * theta.sin() * phi.sin();
@@ -115,7 +191,14 @@ impl GameCamera {
     }
 
     pub fn calculate_rotation(focus: glm::Vec3, pos: glm::Vec3, rotation: f32) -> glm::TVec3<f32> {
-        let up = glm::vec3(0., 1., 0.);
+        let mut up = glm::vec3(0., 1., 0.);
+
+        // Looking straight up or down there's no horizon to take the up-vector from, so we use
+        // the Z axis instead of letting look_at divide by zero.
+        let view = glm::normalize(&(pos - focus));
+        if view.cross(&up).norm() < 1e-4 {
+            up = glm::vec3(0., 0., 1.);
+        }
 
         // Calculate the matrix from the look_at
         let m_look_at = glm::look_at(&focus, &pos, &up);
@@ -130,17 +213,140 @@ impl GameCamera {
         m_new.fixed_rows::<glm::U1>(1).transpose().xyz()
     }
 
-    pub fn clamp_distance(&mut self, point: &glm::Vec3) {
+    /// Inverse of `calculate_rotation`: finds the rotation that gives the up-vector `up`.
+    pub fn calculate_roll(focus: glm::Vec3, pos: glm::Vec3, up: glm::Vec3) -> f32 {
+        let base = GameCamera::calculate_rotation(focus, pos, 0.);
+        let cos = glm::dot(&glm::normalize(&base), &glm::normalize(&up));
+        let angle = cos.clamp(-1., 1.).acos();
+
+        // The angle doesn't tell us the direction, so we try both.
+        let error =
+            |rotation| glm::distance(&GameCamera::calculate_rotation(focus, pos, rotation), &up);
+        if error(angle) <= error(-angle) {
+            angle
+        } else {
+            -angle
+        }
+    }
+
+    /// Moves the camera back to the edge of the sphere of `radius` around `point` if it's outside
+    /// of it, keeping the view.
+    pub fn clamp_distance(&mut self, point: &glm::Vec3, radius: f32) {
         let cp = glm::Vec3::from(self.pos);
         let cf = glm::Vec3::from(self.focus);
         let delta_view = cf - cp;
         let distance = glm::l2_norm(&(point - cp));
-        if distance > 400. {
+        if distance > radius {
             let norm = glm::normalize(&(cp - point));
-            let new_point: glm::Vec3 = *point + norm * 380.;
+            let new_point: glm::Vec3 = *point + norm * radius;
 
             self.pos = new_point.into();
             self.focus = Vec3BE::from(new_point + delta_view);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::InertiaConfig;
+    use crate::inertia::Inertia;
+
+    fn camera() -> GameCamera {
+        GameCamera {
+            pos: glm::vec3(0., 0., 0.).into(),
+            focus: glm::vec3(FOCUS_DISTANCE, 0., 0.).into(),
+            rot: glm::vec3(0., 1., 0.).into(),
+            fov: 0.92.into(),
+        }
+    }
+
+    /// Holds the controls for a second and releases them for another, ticking with `dts`.
+    /// Returns the final position and focus.
+    fn fly(dts: &[f32], inertia: bool) -> (glm::Vec3, glm::Vec3) {
+        let mut camera = camera();
+        let mut inertia = Inertia::new(InertiaConfig {
+            enabled: inertia,
+            ..InertiaConfig::default()
+        });
+        let mut input = Input::new();
+        let mut time = 0.;
+
+        for &dt in dts {
+            input.dt = dt;
+            if time < 1. - 1e-4 {
+                input.delta_pos = (1., 2.);
+                input.delta_altitude = 1.;
+                input.delta_focus = (0.5, -0.2);
+            }
+            inertia.update(&mut input);
+            camera.consume_input(&input);
+            input.reset();
+            time += dt;
+        }
+
+        (camera.pos.into(), camera.focus.into())
+    }
+
+    /// Ticks that repeat `pattern` for 2 seconds.
+    fn ticks(pattern: &[f32]) -> Vec<f32> {
+        let repeats = (2. / pattern.iter().sum::<f32>()).round() as usize;
+        pattern.repeat(repeats)
+    }
+
+    #[test]
+    fn fixed_and_variable_ticks_fly_the_same_way() {
+        // A camera moved by tick instead of by time would fly 25% further with the fixed ticks.
+        let fixed = ticks(&[0.01]);
+        let jittered = ticks(&[0.005, 0.02, 0.01, 0.015]);
+        assert_ne!(fixed.len(), jittered.len());
+
+        for &inertia in &[false, true] {
+            let (fixed_pos, fixed_focus) = fly(&fixed, inertia);
+            let (jittered_pos, jittered_focus) = fly(&jittered, inertia);
+
+            assert!(glm::length(&fixed_pos) > 10.);
+            assert!(glm::distance(&fixed_pos, &jittered_pos) < 0.05);
+            assert!(glm::distance(&fixed_focus, &jittered_focus) < 0.05);
+        }
+    }
+
+    #[test]
+    fn pitching_stops_at_the_limit() {
+        let max_pitch = 89_f32.to_radians();
+
+        for &speed in &[0.3, -0.3, 3., -3., 100., -100.] {
+            let (mut x, mut z, mut y) = (FOCUS_DISTANCE, 0., 0.);
+
+            for _ in 0..1000 {
+                let (new_x, new_z, new_y) =
+                    GameCamera::calc_new_focus_point(x, z, y, 0.01, speed, max_pitch);
+                x = new_x;
+                z = new_z;
+                y = new_y;
+
+                assert!(x.is_finite() && y.is_finite() && z.is_finite());
+                let phi = (x.powi(2) + z.powi(2)).sqrt().atan2(y);
+                assert!((phi - FRAC_PI_2).abs() <= max_pitch + 1e-4, "{}", speed);
+            }
+        }
+    }
+
+    #[test]
+    fn looking_straight_up_or_down_has_an_up_vector() {
+        let pos = glm::vec3(1., 2., 3.);
+
+        for &direction in &[1., -1.] {
+            let focus = pos + glm::vec3(0., direction * FOCUS_DISTANCE, 0.);
+
+            for &roll in &[0., 0.5, -2.] {
+                let up = GameCamera::calculate_rotation(focus, pos, roll);
+                let view = glm::normalize(&(focus - pos));
+
+                assert!(up.iter().all(|v| v.is_finite()), "{:?}", up);
+                assert!((glm::length(&up) - 1.).abs() < 1e-3);
+                assert!(glm::dot(&up, &view).abs() < 1e-3);
+            }
+        }
+    }
+}
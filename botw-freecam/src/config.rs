@@ -0,0 +1,698 @@
+use crate::backend::{buttons, vk, Gamepad};
+use crate::utils::KeyPresses;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Name of the config file, which lives next to the dll.
+pub const CONFIG_FILE: &str = "botw-freecam.toml";
+
+/// Bits used for the triggers, so they can be bound like the rest of the buttons.
+const LEFT_TRIGGER: u32 = 0x10000;
+const RIGHT_TRIGGER: u32 = 0x20000;
+
+/// How much a trigger has to be pressed to count as a button.
+const TRIGGER_THRESHOLD: u8 = 150;
+
+macro_rules! actions {
+    ($($action:ident: $name:literal, [$($key:literal),*], [$($button:literal),*];)*) => {
+        /// Everything that can be bound to a key or a button.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Action {
+            $($action,)*
+        }
+
+        impl Action {
+            pub const ALL: &'static [Action] = &[$(Action::$action,)*];
+
+            /// Name used in the config file.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Action::$action => $name,)*
+                }
+            }
+
+            fn default_keys(&self) -> &'static [&'static str] {
+                match self {
+                    $(Action::$action => &[$($key),*],)*
+                }
+            }
+
+            fn default_buttons(&self) -> &'static [&'static str] {
+                match self {
+                    $(Action::$action => &[$($button),*],)*
+                }
+            }
+        }
+    };
+}
+
+actions! {
+    Activate: "activate", ["F2"], ["LT+B"];
+    MoveForward: "move_forward", ["W"], [];
+    MoveBackward: "move_backward", ["S"], [];
+    MoveLeft: "move_left", ["A"], [];
+    MoveRight: "move_right", ["D"], [];
+    LookUp: "look_up", ["Up"], [];
+    LookDown: "look_down", ["Down"], [];
+    LookLeft: "look_left", ["Left"], [];
+    LookRight: "look_right", ["Right"], [];
+    Ascend: "ascend", ["E"], [];
+    Descend: "descend", ["Q"], [];
+    RotateLeft: "rotate_left", ["PageUp"], ["LB"];
+    RotateRight: "rotate_right", ["PageDown"], ["RB"];
+    ResetRoll: "reset_roll", ["End"], ["LB+RB"];
+    SnapRoll: "snap_roll", ["Shift+End"], ["LS"];
+    FovDecrease: "fov_decrease", ["F5"], ["DpadUp"];
+    FovIncrease: "fov_increase", ["F6"], ["DpadDown"];
+    SpeedDown: "speed_down", ["F3"], ["DpadLeft"];
+    SpeedUp: "speed_up", ["F4"], ["DpadRight"];
+    Accelerate: "accelerate", ["LShift"], ["A"];
+    Decelerate: "decelerate", ["Tab"], ["X"];
+    UnlockCharacter: "unlock_character", ["F7"], [];
+    ToggleInertia: "toggle_inertia", ["N"], [];
+    CaptureTarget: "capture_target", ["V"], [];
+    ToggleOrbit: "toggle_orbit", ["M"], ["RS"];
+    ToggleLookAt: "toggle_look_at", ["K"], [];
+    ToggleSequenceLookAt: "toggle_sequence_look_at", ["Ctrl+K"], [];
+    CycleLeash: "cycle_leash", ["Y"], [];
+
+    AddPoint: "add_point", ["F9"], [];
+    Play: "play", ["F10"], [];
+    PlayLoop: "play_loop", ["L"], [];
+    ClearSequence: "clear_sequence", ["F11"], [];
+    DurationDown: "duration_down", ["O"], [];
+    DurationUp: "duration_up", ["P"], [];
+    PointDurationDown: "point_duration_down", ["Ctrl+O"], [];
+    PointDurationUp: "point_duration_up", ["Ctrl+P"], [];
+    HoldDown: "hold_down", ["G"], [];
+    HoldUp: "hold_up", ["H"], [];
+    ToggleTiming: "toggle_timing", ["T"], [];
+    CycleEasing: "cycle_easing", ["B"], [];
+    CyclePointEasing: "cycle_point_easing", ["Ctrl+B"], [];
+    CycleSpline: "cycle_spline", ["C"], [];
+    SaveSlot: "save_slot", ["Ctrl"], [];
+    LoadSlot: "load_slot", ["Alt"], [];
+    Export: "export", ["X"], [];
+    Import: "import", ["Z"], [];
+
+    SelectPrevious: "select_previous", ["["], [];
+    SelectNext: "select_next", ["]"], [];
+    JumpToPoint: "jump_to_point", ["J"], [];
+    ReplacePoint: "replace_point", ["U"], [];
+    InsertPoint: "insert_point", ["Insert"], [];
+    DeletePoint: "delete_point", ["Delete"], [];
+    MovePointEarlier: "move_point_earlier", ["-"], [];
+    MovePointLater: "move_point_later", ["="], [];
+
+    Pause: "pause", ["Space"], ["A"];
+    Stop: "stop", ["F8"], ["B"];
+    Reverse: "reverse", ["R"], ["X"];
+    ScrubBackward: "scrub_backward", ["Left"], ["DpadLeft"];
+    ScrubForward: "scrub_forward", ["Right"], ["DpadRight"];
+    PlaybackSlower: "playback_slower", ["Down"], ["DpadDown"];
+    PlaybackFaster: "playback_faster", ["Up"], ["DpadUp"];
+    StepBackward: "step_backward", [",", "Left"], ["DpadLeft"];
+    StepForward: "step_forward", [".", "Right"], ["DpadRight"];
+    SkipBackward: "skip_backward", ["["], ["LB"];
+    SkipForward: "skip_forward", ["]"], ["RB"];
+}
+
+const KEY_NAMES: &[(&str, i32)] = &[
+    ("Up", vk::UP),
+    ("Down", vk::DOWN),
+    ("Left", vk::LEFT),
+    ("Right", vk::RIGHT),
+    ("PageUp", vk::PRIOR),
+    ("PageDown", vk::NEXT),
+    ("Home", vk::HOME),
+    ("End", vk::END),
+    ("Insert", vk::INSERT),
+    ("Delete", vk::DELETE),
+    ("Space", vk::SPACE),
+    ("Tab", vk::TAB),
+    ("Enter", vk::RETURN),
+    ("Backspace", vk::BACK),
+    ("Shift", vk::SHIFT),
+    ("LShift", vk::LSHIFT),
+    ("RShift", vk::RSHIFT),
+    ("Ctrl", vk::CONTROL),
+    ("LCtrl", vk::LCONTROL),
+    ("RCtrl", vk::RCONTROL),
+    ("Alt", vk::MENU),
+    ("LAlt", vk::LMENU),
+    ("RAlt", vk::RMENU),
+    ("[", vk::OEM_4),
+    ("]", vk::OEM_6),
+    (",", vk::OEM_COMMA),
+    (".", vk::OEM_PERIOD),
+    ("-", vk::OEM_MINUS),
+    ("=", vk::OEM_PLUS),
+    (";", vk::OEM_1),
+    ("/", vk::OEM_2),
+    ("`", vk::OEM_3),
+    ("\\", vk::OEM_5),
+    ("'", vk::OEM_7),
+];
+
+// Combos are shown in this order, so the ones usually held go first.
+const BUTTON_NAMES: &[(&str, u32)] = &[
+    ("LT", LEFT_TRIGGER),
+    ("RT", RIGHT_TRIGGER),
+    ("LB", buttons::LEFT_SHOULDER as u32),
+    ("RB", buttons::RIGHT_SHOULDER as u32),
+    ("A", buttons::A as u32),
+    ("B", buttons::B as u32),
+    ("X", buttons::X as u32),
+    ("Y", buttons::Y as u32),
+    ("LS", buttons::LEFT_THUMB as u32),
+    ("RS", buttons::RIGHT_THUMB as u32),
+    ("Back", buttons::BACK as u32),
+    ("Start", buttons::START as u32),
+    ("DpadUp", buttons::DPAD_UP as u32),
+    ("DpadDown", buttons::DPAD_DOWN as u32),
+    ("DpadLeft", buttons::DPAD_LEFT as u32),
+    ("DpadRight", buttons::DPAD_RIGHT as u32),
+];
+
+/// Parses names like `W`, `7`, `F10`, `Num4` or `PageUp`.
+fn key_code(name: &str) -> Option<i32> {
+    if let Some((_, code)) = KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        return Some(*code);
+    }
+
+    let upper = name.to_ascii_uppercase();
+    let mut chars = upper.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => return Some(c as i32),
+        _ => {}
+    }
+
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<i32>().ok()) {
+        if (1..=24).contains(&n) {
+            return Some(vk::F1 + n - 1);
+        }
+    }
+
+    if let Some(n) = upper
+        .strip_prefix("NUM")
+        .and_then(|n| n.parse::<i32>().ok())
+    {
+        if (0..=9).contains(&n) {
+            return Some(vk::NUMPAD0 + n);
+        }
+    }
+
+    None
+}
+
+fn key_name(code: i32) -> String {
+    if let Some((name, _)) = KEY_NAMES.iter().find(|(_, c)| *c == code) {
+        return name.to_string();
+    }
+
+    match code {
+        0x30..=0x39 | 0x41..=0x5A => (code as u8 as char).to_string(),
+        c if (vk::F1..vk::F1 + 24).contains(&c) => {
+            format!("F{}", c - vk::F1 + 1)
+        }
+        c if (vk::NUMPAD0..vk::NUMPAD0 + 10).contains(&c) => {
+            format!("Num{}", c - vk::NUMPAD0)
+        }
+        c => format!("0x{:X}", c),
+    }
+}
+
+fn button_mask(name: &str) -> Option<u32> {
+    BUTTON_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, mask)| *mask)
+}
+
+/// Keys that have to be held together. The last one is the one that triggers the action, the
+/// rest work as modifiers.
+#[derive(Debug, Clone, PartialEq)]
+struct KeyCombo(Vec<i32>);
+
+impl KeyCombo {
+    fn parse(combo: &str) -> Result<Self, String> {
+        combo
+            .split('+')
+            .map(|name| key_code(name.trim()).ok_or_else(|| format!("Unknown key `{}`", name)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(KeyCombo)
+    }
+
+    fn held(&self, keys: &KeyPresses) -> bool {
+        self.0.iter().all(|&key| keys.held(key))
+    }
+
+    fn just_pressed(&self, keys: &KeyPresses) -> bool {
+        match self.0.split_last() {
+            Some((&key, modifiers)) => {
+                modifiers.iter().all(|&m| keys.held(m)) && keys.just_pressed(key)
+            }
+            None => false,
+        }
+    }
+
+    /// Whether this is `other` with more modifiers, like `Ctrl+B` and `B`.
+    fn extends(&self, other: &KeyCombo) -> bool {
+        match (self.0.split_last(), other.0.split_last()) {
+            (Some((key, modifiers)), Some((other_key, other_modifiers))) => {
+                key == other_key
+                    && modifiers.len() > other_modifiers.len()
+                    && other_modifiers.iter().all(|m| modifiers.contains(m))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.0.iter().map(|&k| key_name(k)).collect();
+        write!(f, "{}", names.join(" + "))
+    }
+}
+
+/// Buttons that have to be held together, as a mask of the controller state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ButtonCombo(u32);
+
+impl ButtonCombo {
+    fn parse(combo: &str) -> Result<Self, String> {
+        combo
+            .split('+')
+            .map(|name| {
+                button_mask(name.trim()).ok_or_else(|| format!("Unknown button `{}`", name))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|masks| ButtonCombo(masks.iter().fold(0, |acc, m| acc | m)))
+    }
+
+    fn held(&self, state: u32) -> bool {
+        self.0 != 0 && state & self.0 == self.0
+    }
+
+    /// Whether this has every button of `other` and more, like `LB+RB` and `LB`.
+    fn extends(&self, other: &ButtonCombo) -> bool {
+        self.0 != other.0 && self.0 & other.0 == other.0
+    }
+}
+
+impl std::fmt::Display for ButtonCombo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = BUTTON_NAMES
+            .iter()
+            .filter(|(_, mask)| self.0 & mask != 0)
+            .map(|(name, _)| *name)
+            .collect();
+        write!(f, "{}", names.join(" + "))
+    }
+}
+
+/// Buttons and triggers of the controller packed in a single mask.
+pub fn controller_state(gamepad: &Gamepad) -> u32 {
+    let mut state = gamepad.buttons as u32;
+    if gamepad.left_trigger > TRIGGER_THRESHOLD {
+        state |= LEFT_TRIGGER;
+    }
+    if gamepad.right_trigger > TRIGGER_THRESHOLD {
+        state |= RIGHT_TRIGGER;
+    }
+
+    state
+}
+
+/// Keys and buttons bound to every action.
+#[derive(Debug, Clone)]
+pub struct Bindings {
+    keyboard: HashMap<Action, Vec<KeyCombo>>,
+    controller: HashMap<Action, Vec<ButtonCombo>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        FreecamConfig::default()
+            .bindings()
+            .expect("The default bindings are invalid")
+    }
+}
+
+// When a combo is held, the shorter combos it contains don't fire, so `Ctrl+B` only triggers the
+// action bound to it and not the one bound to `B` as well.
+impl Bindings {
+    /// Whether any of the keys of `action` is being held.
+    pub fn held(&self, action: Action, keys: &KeyPresses) -> bool {
+        self.keyboard[&action]
+            .iter()
+            .any(|c| c.held(keys) && !self.key_shadowed(c, keys))
+    }
+
+    /// Whether any of the keys of `action` was pressed since the last tick.
+    pub fn just_pressed(&self, action: Action, keys: &KeyPresses) -> bool {
+        self.keyboard[&action]
+            .iter()
+            .any(|c| c.just_pressed(keys) && !self.key_shadowed(c, keys))
+    }
+
+    /// Whether any of the buttons of `action` is held in `state`.
+    pub fn button_held(&self, action: Action, state: u32) -> bool {
+        self.controller[&action]
+            .iter()
+            .any(|c| c.held(state) && !self.button_shadowed(c, state))
+    }
+
+    /// Whether any of the buttons of `action` is held in `state` but wasn't in `previous`.
+    pub fn button_just_pressed(&self, action: Action, state: u32, previous: u32) -> bool {
+        self.controller[&action]
+            .iter()
+            .any(|c| c.held(state) && !c.held(previous) && !self.button_shadowed(c, state))
+    }
+
+    /// Whether a longer combo that contains `combo` is being held.
+    fn key_shadowed(&self, combo: &KeyCombo, keys: &KeyPresses) -> bool {
+        self.keyboard
+            .values()
+            .flatten()
+            .any(|other| other.extends(combo) && other.held(keys))
+    }
+
+    fn button_shadowed(&self, combo: &ButtonCombo, state: u32) -> bool {
+        self.controller
+            .values()
+            .flatten()
+            .any(|other| other.extends(combo) && other.held(state))
+    }
+
+    /// Human readable keys and buttons of `action`, used in the instructions.
+    pub fn describe(&self, action: Action) -> (String, String) {
+        let keys: Vec<String> = self.keyboard[&action]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let buttons: Vec<String> = self.controller[&action]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        (keys.join(" or "), buttons.join(" or "))
+    }
+}
+
+/// Settings loaded from `CONFIG_FILE`. Actions that aren't in the file keep their default
+/// bindings, and an empty list unbinds them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FreecamConfig {
+    pub keyboard: BTreeMap<String, Vec<String>>,
+    pub controller: BTreeMap<String, Vec<String>>,
+    pub inertia: InertiaConfig,
+    pub camera: CameraConfig,
+    pub startup: StartupConfig,
+}
+
+/// How the freecam waits for the game before patching it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupConfig {
+    /// Seconds to wait for the game to get to the world before giving up, 0 waits forever.
+    pub timeout: u64,
+}
+
+/// Limits of the free camera.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// How far (in degrees) the camera can look up or down from the horizon. Going over 90
+    /// would flip the view.
+    pub max_pitch: f32,
+    /// The game crashes when the camera goes too far from the player, so the camera is kept
+    /// within this distance of where it was activated.
+    pub safety_radius: f32,
+    pub leash: LeashMode,
+}
+
+/// What happens when the camera goes past the safety radius.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LeashMode {
+    Off,
+    /// Only warns in the console.
+    Warn,
+    /// Keeps the camera on the edge of the safety radius.
+    Clamp,
+    /// Clears the sequence, since playing it would take the camera back to where it was.
+    ClearSequence,
+}
+
+impl LeashMode {
+    /// Next mode, used to cycle through them with a single key.
+    pub fn next(&self) -> Self {
+        match self {
+            LeashMode::Off => LeashMode::Warn,
+            LeashMode::Warn => LeashMode::Clamp,
+            LeashMode::Clamp => LeashMode::ClearSequence,
+            LeashMode::ClearSequence => LeashMode::Off,
+        }
+    }
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            max_pitch: 89.,
+            safety_radius: 400.,
+            leash: LeashMode::Clamp,
+        }
+    }
+}
+
+impl CameraConfig {
+    fn validate(&self) -> Result<(), String> {
+        if !(self.max_pitch > 0. && self.max_pitch < 90.) {
+            return Err("`camera.max_pitch` must be between 0 and 90 degrees".to_string());
+        }
+
+        if !self.safety_radius.is_finite() || self.safety_radius <= 0. {
+            return Err("`camera.safety_radius` must be a positive number".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Damping of the smoothed camera motion. Each half-life is the time (in seconds) it takes for
+/// the velocity to cover half of the distance to the one requested by the controls, so 0
+/// disables the smoothing of that part of the camera.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InertiaConfig {
+    /// Whether the smoothing starts enabled.
+    pub enabled: bool,
+    pub position_half_life: f32,
+    pub look_half_life: f32,
+    pub roll_half_life: f32,
+    pub fov_half_life: f32,
+}
+
+impl Default for InertiaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position_half_life: 0.3,
+            look_half_life: 0.15,
+            roll_half_life: 0.2,
+            fov_half_life: 0.2,
+        }
+    }
+}
+
+impl InertiaConfig {
+    fn validate(&self) -> Result<(), String> {
+        let half_lives = [
+            ("position_half_life", self.position_half_life),
+            ("look_half_life", self.look_half_life),
+            ("roll_half_life", self.roll_half_life),
+            ("fov_half_life", self.fov_half_life),
+        ];
+
+        for (name, value) in half_lives.iter() {
+            if !value.is_finite() || *value < 0. {
+                return Err(format!("`inertia.{}` must be a positive number", name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FreecamConfig {
+    fn default() -> Self {
+        let defaults = |bindings: fn(&Action) -> &'static [&'static str]| {
+            Action::ALL
+                .iter()
+                .map(|a| {
+                    (
+                        a.name().to_string(),
+                        bindings(a).iter().map(|b| b.to_string()).collect(),
+                    )
+                })
+                .collect()
+        };
+
+        Self {
+            keyboard: defaults(Action::default_keys),
+            controller: defaults(Action::default_buttons),
+            inertia: InertiaConfig::default(),
+            camera: CameraConfig::default(),
+            startup: StartupConfig::default(),
+        }
+    }
+}
+
+impl FreecamConfig {
+    /// Loads the config from `path`, writing the default one if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            let config = FreecamConfig::default();
+            std::fs::write(path, toml::to_string(&config)?)?;
+            return Ok(config);
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        let config: FreecamConfig = toml::from_str(&contents)
+            .map_err(|e| format!("{} is not a valid config: {}", path.display(), e))?;
+        config.bindings()?;
+        config.inertia.validate()?;
+        config.camera.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn bindings(&self) -> Result<Bindings, String> {
+        for name in self.keyboard.keys().chain(self.controller.keys()) {
+            if !Action::ALL.iter().any(|a| a.name() == name) {
+                return Err(format!("Unknown action `{}`", name));
+            }
+        }
+
+        let names = |config: &BTreeMap<String, Vec<String>>, action: Action, defaults: &[&str]| {
+            config
+                .get(action.name())
+                .cloned()
+                .unwrap_or_else(|| defaults.iter().map(|d| d.to_string()).collect::<Vec<_>>())
+        };
+        let context = |action: Action| move |e: String| format!("{} in `{}`", e, action.name());
+
+        let mut keyboard = HashMap::new();
+        let mut controller = HashMap::new();
+        for &action in Action::ALL {
+            let keys = names(&self.keyboard, action, action.default_keys())
+                .iter()
+                .map(|k| KeyCombo::parse(k))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(context(action))?;
+            let buttons = names(&self.controller, action, action.default_buttons())
+                .iter()
+                .map(|b| ButtonCombo::parse(b))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(context(action))?;
+
+            keyboard.insert(action, keys);
+            controller.insert(action, buttons);
+        }
+
+        Ok(Bindings {
+            keyboard,
+            controller,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Frame, ScriptedInput};
+    use crate::utils::Keys;
+
+    /// Keys after pressing `keys` on a tick where nothing was held.
+    fn press(keys: &[i32]) -> KeyPresses {
+        let mut source = ScriptedInput::new(vec![
+            Frame::default(),
+            Frame {
+                keys: keys.to_vec(),
+                ..Frame::default()
+            },
+        ]);
+        let mut presses = KeyPresses::default();
+        presses.update(&source);
+        source.advance();
+        presses.update(&source);
+        presses
+    }
+
+    #[test]
+    fn combos_with_modifiers_dont_fire_the_plain_key() {
+        let bindings = Bindings::default();
+
+        let keys = press(&[Keys::B as i32]);
+        assert!(bindings.just_pressed(Action::CycleEasing, &keys));
+        assert!(!bindings.just_pressed(Action::CyclePointEasing, &keys));
+
+        let keys = press(&[vk::CONTROL, Keys::B as i32]);
+        assert!(bindings.just_pressed(Action::CyclePointEasing, &keys));
+        assert!(!bindings.just_pressed(Action::CycleEasing, &keys));
+
+        let keys = press(&[vk::SHIFT, vk::END]);
+        assert!(bindings.just_pressed(Action::SnapRoll, &keys));
+        assert!(!bindings.just_pressed(Action::ResetRoll, &keys));
+
+        let keys = press(&[vk::CONTROL, Keys::P as i32]);
+        assert!(bindings.held(Action::PointDurationUp, &keys));
+        assert!(!bindings.held(Action::DurationUp, &keys));
+
+        // Ctrl is also bound on its own, which doesn't stop the longer combo.
+        assert!(bindings.held(Action::SaveSlot, &keys));
+    }
+
+    #[test]
+    fn sequence_look_at_doesnt_toggle_the_look_at_lock() {
+        let bindings = Bindings::default();
+
+        let keys = press(&[vk::CONTROL, Keys::K as i32]);
+        assert!(bindings.just_pressed(Action::ToggleSequenceLookAt, &keys));
+        assert!(!bindings.just_pressed(Action::ToggleLookAt, &keys));
+
+        let keys = press(&[Keys::K as i32]);
+        assert!(bindings.just_pressed(Action::ToggleLookAt, &keys));
+        assert!(!bindings.just_pressed(Action::ToggleSequenceLookAt, &keys));
+    }
+
+    #[test]
+    fn button_combos_dont_fire_the_buttons_they_contain() {
+        let bindings = Bindings::default();
+        let lb = buttons::LEFT_SHOULDER as u32;
+        let rb = buttons::RIGHT_SHOULDER as u32;
+
+        assert!(bindings.button_held(Action::RotateLeft, lb));
+        assert!(bindings.button_just_pressed(Action::ResetRoll, lb | rb, lb));
+        assert!(!bindings.button_held(Action::RotateLeft, lb | rb));
+        assert!(!bindings.button_held(Action::RotateRight, lb | rb));
+    }
+
+    #[test]
+    fn user_combos_are_resolved_the_same_way() {
+        let mut config = FreecamConfig::default();
+        config
+            .keyboard
+            .insert("add_point".to_string(), vec!["Alt+Shift+J".to_string()]);
+        let bindings = config.bindings().unwrap();
+
+        let keys = press(&[vk::MENU, vk::SHIFT, Keys::J as i32]);
+        assert!(bindings.just_pressed(Action::AddPoint, &keys));
+        assert!(!bindings.just_pressed(Action::JumpToPoint, &keys));
+        assert!(bindings.just_pressed(Action::JumpToPoint, &press(&[Keys::J as i32])));
+    }
+}
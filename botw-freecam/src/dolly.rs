@@ -1,19 +1,35 @@
 use crate::camera::*;
+use crate::easing::Easing;
+use crate::orientation::{self, Orientation};
+use crate::spline::Spline;
 use crate::utils::*;
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
-use winapi::um::winuser;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CameraSnapshot {
+    #[serde(with = "crate::sequence::vec3")]
     pub pos: glm::TVec3<f32>,
+    #[serde(with = "crate::sequence::vec3")]
     pub focus: glm::TVec3<f32>,
+    #[serde(with = "crate::sequence::vec3")]
     pub rot: glm::TVec3<f32>,
     pub fov: f32,
-}
 
-pub trait Interpolate {
-    fn interpolate(&self, gc: &mut GameCamera, duration: Duration, loop_it: bool);
+    /// Seconds it takes to travel from the previous keyframe to this one. When it's not set the
+    /// sequence duration is spread evenly between the segments.
+    #[serde(default)]
+    pub duration: Option<f32>,
+
+    /// Seconds the camera stays still on this keyframe once it reaches it.
+    #[serde(default)]
+    pub hold: f32,
+
+    /// Easing of the segment that arrives to this keyframe. When it's not set the segment moves
+    /// linearly.
+    #[serde(default)]
+    pub easing: Option<Easing>,
 }
 
 impl CameraSnapshot {
@@ -28,9 +44,47 @@ impl CameraSnapshot {
             focus,
             rot,
             fov,
+            duration: None,
+            hold: 0.,
+            easing: None,
         }
     }
 
+    /// Changes the keyframe's own duration and hold time. If the duration wasn't set it starts
+    /// from `default_duration`, and going under the minimum sets it back to the default.
+    pub fn adjust_timing(&mut self, default_duration: f32, delta_duration: f32, delta_hold: f32) {
+        if delta_duration != 0. {
+            let duration = self.duration.unwrap_or(default_duration) + delta_duration;
+            self.duration = limit_duration(duration);
+        }
+
+        self.hold = limit_hold(self.hold + delta_hold);
+    }
+
+    /// Applies the limits of `adjust_timing` to a keyframe that wasn't made with the controls.
+    pub fn limit_timing(&mut self) {
+        self.duration = self.duration.and_then(limit_duration);
+        self.hold = limit_hold(self.hold);
+    }
+
+    /// Whether the keyframe can be given to the game: every value is finite and the fov is one
+    /// the controls allow.
+    pub fn is_valid(&self) -> bool {
+        let finite = self
+            .pos
+            .iter()
+            .chain(self.focus.iter())
+            .chain(self.rot.iter())
+            .all(|v| v.is_finite());
+
+        finite && self.fov > 0. && self.fov <= MAX_FOV
+    }
+
+    /// Rotation of the camera, built from the view direction and the up-vector.
+    pub fn orientation(&self) -> glm::Quat {
+        orientation::to_quat(&(self.focus - self.pos), &self.rot)
+    }
+
     pub fn set_inplace(&self, gc: &mut GameCamera) {
         gc.pos = self.pos.into();
         gc.focus = self.focus.into();
@@ -39,51 +93,160 @@ impl CameraSnapshot {
     }
 }
 
-fn solve_eq(t: f32, p0: glm::Vec3, p1: glm::Vec3, p2: glm::Vec3, p3: glm::Vec3) -> glm::Vec3 {
-    let b0 = 0.5 * (-t.powi(3) + 2. * t.powi(2) - t);
-    let b1 = 0.5 * (3. * t.powi(3) - 5. * t.powi(2) + 2.);
-    let b2 = 0.5 * (-3. * t.powi(3) + 4. * t.powi(2) + t);
-    let b3 = 0.5 * (t.powi(3) - t.powi(2));
+/// Durations under the minimum (or invalid ones) fall back to the duration of the sequence.
+fn limit_duration(duration: f32) -> Option<f32> {
+    if duration >= 0.1 {
+        Some(duration)
+    } else {
+        None
+    }
+}
+
+fn limit_hold(hold: f32) -> f32 {
+    hold.max(0.)
+}
+
+/// How the time of the sequence is distributed along the path.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Timing {
+    /// Every segment takes the same time no matter how long it is, so the camera speeds up on
+    /// long segments and slows down on short ones.
+    #[default]
+    Uniform,
+    /// The spline is reparameterized by its arc length so the camera moves at a constant speed.
+    ConstantSpeed,
+}
 
-    p0 * b0 + p1 * b1 + p2 * b2 + p3 * b3
+/// Keyframes of a dolly plus the settings used to play them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sequence {
+    pub points: Vec<CameraSnapshot>,
+    #[serde(default)]
+    pub spline: Spline,
+    #[serde(default)]
+    pub orientation: Orientation,
+    #[serde(default)]
+    pub timing: Timing,
+    /// Easing applied to the whole sequence, on top of the easing of every segment.
+    #[serde(default)]
+    pub easing: Easing,
+    /// Point the camera keeps looking at while the sequence plays, instead of the view of the
+    /// points. The roll of the points is kept.
+    #[serde(
+        default,
+        with = "crate::sequence::optional_vec3",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub look_at: Option<glm::Vec3>,
 }
 
+/// Amount of samples used to approximate the arc length of a segment.
+const ARC_LENGTH_SAMPLES: usize = 32;
+
+/// Cumulative arc length of a segment, sampled at regular `t` steps.
+#[derive(Debug, Clone)]
+struct ArcLengthTable(Vec<f32>);
+
+impl ArcLengthTable {
+    fn new(curve: impl Fn(f32) -> glm::Vec3) -> Self {
+        let mut lengths = Vec::with_capacity(ARC_LENGTH_SAMPLES + 1);
+        let mut previous = curve(0.);
+        let mut length = 0.;
+        lengths.push(length);
 
-/// We create our own sleep since we need a more precise way of sleeping the process. The way we'll
-/// do that is to sleep 1 ms always and then check if enough time has passed. It's like a
-/// combination between busy waiting and sleeping entirely.
-fn sleep(dur: Duration) {
-    let starting_point = std::time::Instant::now();
-    loop {
-        if starting_point.elapsed() >= dur {
-            break;
+        for i in 1..=ARC_LENGTH_SAMPLES {
+            let current = curve(i as f32 / ARC_LENGTH_SAMPLES as f32);
+            length += calc_eucl_distance(&previous, &current);
+            lengths.push(length);
+            previous = current;
         }
-        std::thread::sleep(Duration::from_millis(1));
+
+        Self(lengths)
+    }
+
+    fn length(&self) -> f32 {
+        *self.0.last().unwrap()
     }
+
+    /// Returns the `t` at which the `u` fraction of the segment length has been travelled.
+    fn t_at(&self, u: f32) -> f32 {
+        let target = u * self.length();
+        let i = match self.0.iter().position(|&l| l >= target) {
+            Some(0) | None => return u,
+            Some(i) => i,
+        };
+
+        let (l0, l1) = (self.0[i - 1], self.0[i]);
+        let fraction = if l1 > l0 {
+            (target - l0) / (l1 - l0)
+        } else {
+            0.
+        };
+
+        (i as f32 - 1. + fraction) / ARC_LENGTH_SAMPLES as f32
+    }
+}
+
+/// A segment of the timeline: the camera waits `hold` seconds on the keyframe and then
+/// travels to the next one in `duration` seconds.
+#[derive(Debug, Clone)]
+struct Segment {
+    start: f32,
+    hold: f32,
+    duration: f32,
+    easing: Easing,
+    arc_length: Option<ArcLengthTable>,
 }
 
-impl Interpolate for Vec<CameraSnapshot> {
-    fn interpolate(&self, gc: &mut GameCamera, duration: Duration, loop_it: bool) {
-        let sleep_duration = Duration::from_millis(10);
+impl Segment {
+    fn end(&self) -> f32 {
+        self.start + self.hold + self.duration
+    }
+
+    /// Converts the time elapsed since the start of the segment into the `t` of the spline.
+    fn spline_t(&self, elapsed: f32) -> f32 {
+        if self.duration <= 0. {
+            return 0.;
+        }
+
+        let u = self
+            .easing
+            .apply((elapsed - self.hold) / self.duration)
+            .clamp(0., 1.);
+        match &self.arc_length {
+            Some(table) => table.t_at(u),
+            None => u,
+        }
+    }
+}
 
-        let fraction = sleep_duration.as_secs_f32() / duration.as_secs_f32();
+impl Sequence {
+    fn n_segments(&self, loop_it: bool) -> usize {
+        if loop_it {
+            self.points.len()
+        } else {
+            self.points.len() - 1
+        }
+    }
 
-        self[0].set_inplace(gc);
+    /// Indices of the four points that control the segment that starts on `segment`.
+    fn control_points(&self, segment: usize, loop_it: bool) -> [usize; 4] {
+        let len = self.points.len() as i32;
 
         macro_rules! bounds {
             ($var:expr) => {
                 // TODO: Check if this was the issue with the smooth transition
                 if $var < 0 {
                     if loop_it {
-                        (self.len() - 1) as i32
+                        len - 1
                     } else {
                         0
                     }
-                } else if $var >= (self.len() - 1) as i32 {
+                } else if $var >= len - 1 {
                     if loop_it {
-                        $var % (self.len()) as i32
+                        $var % len
                     } else {
-                        (self.len() - 1) as i32
+                        len - 1
                     }
                 } else {
                     $var
@@ -91,51 +254,292 @@ impl Interpolate for Vec<CameraSnapshot> {
             };
         }
 
-        let delta_t = if loop_it {
-            1. / ((self.len()) as f32)
-        } else {
-            1. / ((self.len() - 1) as f32)
+        let i = segment as i32;
+        [
+            bounds!(i - 1) as usize,
+            bounds!(i) as usize,
+            bounds!(i + 1) as usize,
+            bounds!(i + 2) as usize,
+        ]
+    }
+
+    /// Evaluates the camera at `t` (0 to 1) of the segment that starts on `segment`.
+    fn sample(&self, segment: usize, t: f32, loop_it: bool) -> CameraSnapshot {
+        let [p0, p1, p2, p3] = self.control_points(segment, loop_it);
+        let points = &self.points;
+
+        let fov = glm::lerp_scalar(points[p1].fov, points[p2].fov, glm::smoothstep(0., 1., t));
+        let pos = self.spline.solve_eq(
+            t,
+            points[p0].pos,
+            points[p1].pos,
+            points[p2].pos,
+            points[p3].pos,
+        );
+
+        // Interpolating focus and up-vector independently makes the camera wobble, so we
+        // interpolate the rotation instead and rebuild both from it.
+        let q = self.orientation.interpolate(
+            t,
+            &points[p0].orientation(),
+            &points[p1].orientation(),
+            &points[p2].orientation(),
+            &points[p3].orientation(),
+        );
+        let (forward, up) = orientation::from_quat(&q);
+        let distance = glm::lerp_scalar(
+            glm::distance(&points[p1].pos, &points[p1].focus),
+            glm::distance(&points[p2].pos, &points[p2].focus),
+            t,
+        );
+
+        let mut snapshot = CameraSnapshot {
+            pos,
+            focus: pos + forward * distance,
+            rot: up,
+            fov,
+            duration: None,
+            hold: 0.,
+            easing: None,
         };
 
-        'outer: loop {
-            let mut t = 0.;
-            while t < 1. {
-                if check_key_press(winuser::VK_F8) {
-                    break 'outer;
-                }
+        if let Some(target) = self.look_at {
+            if glm::distance(&pos, &target) > 1e-3 {
+                let roll = GameCamera::calculate_roll(snapshot.focus, pos, up);
+                snapshot.focus = target;
+                snapshot.rot = GameCamera::calculate_rotation(target, pos, roll);
+            }
+        }
+
+        snapshot
+    }
+
+    fn arc_length(&self, segment: usize, loop_it: bool) -> ArcLengthTable {
+        let [p0, p1, p2, p3] = self.control_points(segment, loop_it);
+        let points = &self.points;
+
+        ArcLengthTable::new(|t| {
+            self.spline.solve_eq(
+                t,
+                points[p0].pos,
+                points[p1].pos,
+                points[p2].pos,
+                points[p3].pos,
+            )
+        })
+    }
 
-                let p: i32 = (t / delta_t) as i32;
-                let p0 = bounds!(p - 1) as usize;
-                let p1 = bounds!(p) as usize;
-                let p2 = bounds!(p + 1) as usize;
-                let p3 = bounds!(p + 2) as usize;
-
-                let rt = (t - delta_t * (p as f32)) / delta_t;
-
-                let fov = glm::lerp_scalar(self[p1].fov, self[p2].fov, glm::smoothstep(0., 1., rt));
-                let pos = solve_eq(rt, self[p0].pos, self[p1].pos, self[p2].pos, self[p3].pos);
-                let focus = solve_eq(
-                    rt,
-                    self[p0].focus,
-                    self[p1].focus,
-                    self[p2].focus,
-                    self[p3].focus,
-                );
-                let rot = solve_eq(rt, self[p0].rot, self[p1].rot, self[p2].rot, self[p3].rot);
-                let vec = CameraSnapshot {
-                    pos,
-                    focus,
-                    rot,
-                    fov,
-                };
-                vec.set_inplace(gc);
-                t += fraction;
-                sleep(sleep_duration);
+    /// Builds the timeline of the sequence. Keyframes without their own duration share the
+    /// default one, which is `duration` spread evenly across the segments, or proportionally to
+    /// their length when the timing is `ConstantSpeed`.
+    fn build_timeline(&self, duration: Duration, loop_it: bool) -> Vec<Segment> {
+        let n_segments = self.n_segments(loop_it);
+
+        let arc_lengths: Vec<Option<ArcLengthTable>> = (0..self.points.len())
+            .map(|i| match self.timing {
+                Timing::ConstantSpeed if i < n_segments => Some(self.arc_length(i, loop_it)),
+                _ => None,
+            })
+            .collect();
+
+        let total_length: f32 = arc_lengths.iter().flatten().map(|a| a.length()).sum();
+        let default_duration = |arc_length: &Option<ArcLengthTable>| match arc_length {
+            Some(table) if total_length > 1e-3 => {
+                duration.as_secs_f32() * table.length() / total_length
             }
+            _ => duration.as_secs_f32() / (n_segments as f32),
+        };
+
+        let mut start = 0.;
+        let mut timeline = Vec::with_capacity(self.points.len());
+        for (i, (point, arc_length)) in self.points.iter().zip(arc_lengths).enumerate() {
+            let next = &self.points[(i + 1) % self.points.len()];
+
+            // When we don't loop, the last keyframe only holds the camera.
+            let duration = if i < n_segments {
+                next.duration
+                    .unwrap_or_else(|| default_duration(&arc_length))
+            } else {
+                0.
+            };
+
+            let segment = Segment {
+                start,
+                hold: point.hold,
+                duration,
+                easing: next.easing.unwrap_or_default(),
+                arc_length,
+            };
+            start = segment.end();
+            timeline.push(segment);
+        }
+
+        timeline
+    }
+
+    /// Samples the camera `fps` times per second along the sequence played once.
+    pub fn sample_path(&self, duration: Duration, fps: f32) -> Vec<(f32, CameraSnapshot)> {
+        let mut playback = Playback::new(self, duration, false);
+        let frames = (playback.total() * fps).ceil() as usize;
+
+        (0..=frames)
+            .map(|frame| {
+                let time = (frame as f32 / fps).min(playback.total());
+                playback.elapsed = time;
+                (time, playback.snapshot())
+            })
+            .collect()
+    }
+}
+
+/// A sequence being played. Instead of blocking until it finishes, it's advanced once per tick
+/// from the main loop so the rest of the input keeps working.
+pub struct Playback {
+    sequence: Sequence,
+    timeline: Vec<Segment>,
+    total: f32,
+    loop_it: bool,
+
+    /// Seconds since the start of the sequence.
+    pub elapsed: f32,
+    pub speed: f32,
+    pub paused: bool,
+    pub reverse: bool,
+}
+
+/// Time of a single frame when stepping through a paused sequence.
+const FRAME_TIME: f32 = 1. / 30.;
+
+impl Playback {
+    pub fn new(sequence: &Sequence, duration: Duration, loop_it: bool) -> Self {
+        let timeline = sequence.build_timeline(duration, loop_it);
+        let total = timeline.last().unwrap().end();
+
+        Self {
+            sequence: sequence.clone(),
+            timeline,
+            total,
+            loop_it,
+            elapsed: 0.,
+            speed: 1.,
+            paused: false,
+            reverse: false,
+        }
+    }
+
+    /// Advances the playback `dt` seconds and moves the camera. Returns `false` once the
+    /// sequence has finished.
+    pub fn tick(&mut self, gc: &mut GameCamera, dt: f32) -> bool {
+        if !self.paused {
+            let direction = if self.reverse { -1. } else { 1. };
+            self.scrub(dt * self.speed * direction);
+        }
+
+        self.apply(gc);
+
+        if self.paused || self.loop_it {
+            return true;
+        }
+
+        if self.reverse {
+            self.elapsed > 0.
+        } else {
+            self.elapsed < self.total
+        }
+    }
+
+    /// Moves the playback `frames` frames forward (or backward if negative).
+    pub fn step(&mut self, frames: i32) {
+        self.scrub(frames as f32 * FRAME_TIME);
+    }
+
+    /// Moves the playback `seconds` forward (or backward if negative).
+    pub fn scrub(&mut self, seconds: f32) {
+        self.elapsed += seconds;
+
+        if self.loop_it {
+            self.elapsed = self.elapsed.rem_euclid(self.total);
+        } else {
+            self.elapsed = self.elapsed.clamp(0., self.total);
+        }
+    }
+
+    /// Time of the timeline (after the easing of the whole sequence) at the current point of the
+    /// playback, and the segment it falls in.
+    fn position(&self) -> (f32, usize) {
+        // The easing can overshoot, so the time doesn't always go forward.
+        let t = self.total * self.sequence.easing.apply(self.elapsed / self.total);
+        let p = self
+            .timeline
+            .iter()
+            .position(|segment| t < segment.end())
+            .unwrap_or(self.timeline.len() - 1);
+
+        (t, p)
+    }
 
-            if !loop_it {
-                break;
+    /// Moves the playback to the moment the camera reaches the `keyframe`.
+    pub fn jump_to_keyframe(&mut self, keyframe: usize) {
+        let keyframe = if self.loop_it {
+            keyframe % self.timeline.len()
+        } else {
+            keyframe.min(self.timeline.len() - 1)
+        };
+        let target = self.timeline[keyframe].start / self.total;
+
+        // Invert the easing of the sequence with a bisection, since the easing profiles go from 0
+        // to 1.
+        let (mut low, mut high) = (0., 1.);
+        for _ in 0..32 {
+            let mid = (low + high) / 2.;
+            if self.sequence.easing.apply(mid) < target {
+                low = mid;
+            } else {
+                high = mid;
             }
         }
+
+        self.elapsed = high * self.total;
+    }
+
+    /// Jumps `n` keyframes forward (or backward if negative) from the current position.
+    /// Returns the keyframe it landed on.
+    pub fn skip_keyframes(&mut self, n: i32) -> usize {
+        let (t, p) = self.position();
+        let len = self.timeline.len() as i32;
+
+        // If we already left the keyframe, going back one should land on it.
+        let current = if n < 0 && t > self.timeline[p].start + FRAME_TIME {
+            p as i32 + 1
+        } else {
+            p as i32
+        };
+
+        let keyframe = if self.loop_it {
+            (current + n).rem_euclid(len)
+        } else {
+            (current + n).clamp(0, len - 1)
+        } as usize;
+
+        self.jump_to_keyframe(keyframe);
+        keyframe
+    }
+
+    /// Camera at the current time of the playback.
+    pub fn snapshot(&self) -> CameraSnapshot {
+        let (t, p) = self.position();
+        let segment = &self.timeline[p];
+
+        let rt = segment.spline_t(t - segment.start);
+        self.sequence.sample(p, rt, self.loop_it)
+    }
+
+    /// Sets the camera at the current time of the playback.
+    pub fn apply(&self, gc: &mut GameCamera) {
+        self.snapshot().set_inplace(gc);
+    }
+
+    pub fn total(&self) -> f32 {
+        self.total
     }
 }
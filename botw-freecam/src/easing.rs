@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+/// Easing profile that maps the linear progress of the playback (0 to 1) into the progress of the
+/// camera along the path.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Same as CSS' `cubic-bezier`: a curve from (0, 0) to (1, 1) with two control points.
+    CubicBezier {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    },
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t.powi(3),
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4. * t.powi(3)
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                let s = solve_bezier(t, x1.clamp(0., 1.), x2.clamp(0., 1.));
+                bezier(s, y1, y2)
+            }
+        }
+    }
+
+    /// Next profile, used to cycle through them with a single key.
+    pub fn next(&self) -> Self {
+        match self {
+            Easing::Linear => Easing::EaseIn,
+            Easing::EaseIn => Easing::EaseOut,
+            Easing::EaseOut => Easing::EaseInOut,
+            // Same curve as CSS' `ease`.
+            Easing::EaseInOut => Easing::CubicBezier {
+                x1: 0.25,
+                y1: 0.1,
+                x2: 0.25,
+                y2: 1.,
+            },
+            Easing::CubicBezier { .. } => Easing::Linear,
+        }
+    }
+}
+
+/// One dimension of a cubic Bézier that starts on 0 and ends on 1.
+fn bezier(s: f32, p1: f32, p2: f32) -> f32 {
+    let inv = 1. - s;
+    3. * inv.powi(2) * s * p1 + 3. * inv * s.powi(2) * p2 + s.powi(3)
+}
+
+fn bezier_derivative(s: f32, p1: f32, p2: f32) -> f32 {
+    let inv = 1. - s;
+    3. * inv.powi(2) * p1 + 6. * inv * s * (p2 - p1) + 3. * s.powi(2) * (1. - p2)
+}
+
+/// Finds the `s` where the x component of the curve equals `x`. Since the control points are in
+/// [0, 1] the curve is monotonic on x, so we can use Newton's method and fall back to bisection
+/// when the derivative is too flat.
+fn solve_bezier(x: f32, x1: f32, x2: f32) -> f32 {
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(s, x1, x2) - x;
+        if error.abs() < 1e-5 {
+            return s;
+        }
+
+        let derivative = bezier_derivative(s, x1, x2);
+        if derivative.abs() < 1e-6 {
+            break;
+        }
+        s = (s - error / derivative).clamp(0., 1.);
+    }
+
+    let (mut low, mut high) = (0., 1.);
+    s = x;
+    for _ in 0..32 {
+        let value = bezier(s, x1, x2);
+        if (value - x).abs() < 1e-5 {
+            break;
+        }
+
+        if value < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.;
+    }
+
+    s
+}
@@ -0,0 +1,201 @@
+use crate::dolly::{CameraSnapshot, Sequence};
+use crate::orientation;
+use nalgebra_glm as glm;
+use serde_json::json;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// Frames per second of the exported paths.
+pub const EXPORT_FPS: f32 = 30.;
+
+/// The game's fov is vertical, but Blender's focal length depends on the sensor width, so we
+/// assume a 16:9 image on Blender's default 36mm sensor.
+const ASPECT_RATIO: f32 = 16. / 9.;
+const SENSOR_WIDTH: f32 = 36.;
+
+/// Converts a vertical fov (in radians) to a focal length in millimeters.
+pub fn fov_to_focal_length(fov: f32) -> f32 {
+    let horizontal = 2. * ((fov / 2.).tan() * ASPECT_RATIO).atan();
+    (SENSOR_WIDTH / 2.) / (horizontal / 2.).tan()
+}
+
+/// Inverse of `fov_to_focal_length`.
+pub fn focal_length_to_fov(focal_length: f32) -> f32 {
+    let horizontal = 2. * ((SENSOR_WIDTH / 2.) / focal_length).atan();
+    2. * ((horizontal / 2.).tan() / ASPECT_RATIO).atan()
+}
+
+/// Converts a point from the game (Y up) to Blender (Z up).
+pub fn to_blender(v: &glm::Vec3) -> glm::Vec3 {
+    glm::vec3(v[0], -v[2], v[1])
+}
+
+/// Converts a point from Blender (Z up) to the game (Y up).
+pub fn from_blender(v: &glm::Vec3) -> glm::Vec3 {
+    glm::vec3(v[0], v[2], -v[1])
+}
+
+/// Writes one row per frame with the position, target and up-vector of the camera in Blender's
+/// coordinates, plus its fov and focal length.
+pub fn export_csv(
+    path: &Path,
+    samples: &[(f32, CameraSnapshot)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(
+        file,
+        "frame,time,pos_x,pos_y,pos_z,target_x,target_y,target_z,up_x,up_y,up_z,fov,focal_length"
+    )?;
+
+    for (frame, (time, snapshot)) in samples.iter().enumerate() {
+        let pos = to_blender(&snapshot.pos);
+        let target = to_blender(&snapshot.focus);
+        let up = to_blender(&snapshot.rot);
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            frame,
+            time,
+            pos[0],
+            pos[1],
+            pos[2],
+            target[0],
+            target[1],
+            target[2],
+            up[0],
+            up[1],
+            up[2],
+            snapshot.fov,
+            fov_to_focal_length(snapshot.fov)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes a glTF 2.0 file with an animated camera. The keyframes are stored in a `.bin` file
+/// next to it. Since glTF is Y up like the game, Blender's importer takes care of the axes.
+pub fn export_gltf(
+    path: &Path,
+    samples: &[(f32, CameraSnapshot)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bin_path = path.with_extension("bin");
+    let bin_name = bin_path
+        .file_name()
+        .ok_or("Invalid glTF path")?
+        .to_string_lossy()
+        .to_string();
+
+    let count = samples.len();
+    let mut times = Vec::with_capacity(count);
+    let mut translations = Vec::with_capacity(count * 3);
+    let mut rotations = Vec::with_capacity(count * 4);
+    let mut fovs = Vec::with_capacity(count);
+
+    for (time, snapshot) in samples {
+        // glTF cameras look towards -Z.
+        let q = orientation::to_quat(&(snapshot.pos - snapshot.focus), &snapshot.rot);
+        times.push(*time);
+        translations.extend_from_slice(&[snapshot.pos[0], snapshot.pos[1], snapshot.pos[2]]);
+        rotations.extend_from_slice(&[q[0], q[1], q[2], q[3]]);
+        fovs.push(snapshot.fov);
+    }
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    for data in [&times, &translations, &rotations, &fovs].iter() {
+        views.push(json!({
+            "buffer": 0,
+            "byteOffset": buffer.len(),
+            "byteLength": data.len() * 4,
+        }));
+        for value in data.iter() {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    let first = samples.first().ok_or("There are no samples to export")?;
+    let gltf = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": format!("botw-freecam v{}", crate::utils::get_version()),
+        },
+        "extensionsUsed": ["KHR_animation_pointer"],
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "name": "Camera", "camera": 0 }],
+        "cameras": [{
+            "type": "perspective",
+            "perspective": {
+                "aspectRatio": ASPECT_RATIO,
+                "yfov": first.1.fov,
+                "znear": 0.1,
+                "zfar": 10000.,
+            },
+        }],
+        "buffers": [{ "uri": bin_name, "byteLength": buffer.len() }],
+        "bufferViews": views,
+        "accessors": [
+            {
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": count,
+                "type": "SCALAR",
+                "min": [times[0]],
+                "max": [times[count - 1]],
+            },
+            { "bufferView": 1, "componentType": 5126, "count": count, "type": "VEC3" },
+            { "bufferView": 2, "componentType": 5126, "count": count, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": count, "type": "SCALAR" },
+        ],
+        "animations": [{
+            "name": "Dolly",
+            "samplers": [
+                { "input": 0, "output": 1, "interpolation": "LINEAR" },
+                { "input": 0, "output": 2, "interpolation": "LINEAR" },
+                { "input": 0, "output": 3, "interpolation": "LINEAR" },
+            ],
+            "channels": [
+                { "sampler": 0, "target": { "node": 0, "path": "translation" } },
+                { "sampler": 1, "target": { "node": 0, "path": "rotation" } },
+                {
+                    "sampler": 2,
+                    "target": {
+                        "path": "pointer",
+                        "extensions": {
+                            "KHR_animation_pointer": {
+                                "pointer": "/cameras/0/perspective/yfov",
+                            },
+                        },
+                    },
+                },
+            ],
+        }],
+    });
+
+    std::fs::write(&bin_path, buffer)?;
+    std::fs::write(path, serde_json::to_string_pretty(&gltf)?)?;
+
+    Ok(())
+}
+
+/// Samples the sequence and exports it as `<name>.csv` and `<name>.gltf` inside `dir`.
+pub fn export_sequence(
+    dir: &Path,
+    name: &str,
+    sequence: &Sequence,
+    duration: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if sequence.points.len() < 2 {
+        return Err("The sequence needs at least two points".into());
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let samples = sequence.sample_path(duration, EXPORT_FPS);
+
+    export_csv(&dir.join(format!("{}.csv", name)), &samples)?;
+    export_gltf(&dir.join(format!("{}.gltf", name)), &samples)?;
+
+    Ok(())
+}
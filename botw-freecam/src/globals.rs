@@ -8,5 +8,6 @@ memory_rs::scoped_no_mangle! {
 
 extern "C" {
     pub static asm_get_camera_data: u8;
+    pub static asm_get_camera_data_bswap: u8;
     pub static asm_override_xinput_call: u8;
 }
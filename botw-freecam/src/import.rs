@@ -0,0 +1,414 @@
+use crate::dolly::CameraSnapshot;
+use crate::export::{focal_length_to_fov, from_blender};
+use crate::orientation;
+use crate::utils::calc_eucl_distance;
+use nalgebra_glm as glm;
+use serde_json::Value;
+use std::path::Path;
+
+type ImportResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Loads the keyframes of a camera path made in another program, like the ones written by
+/// `export`. The files can be a glTF (`.gltf` or `.glb`) with an animated camera or a CSV in
+/// Blender's coordinates.
+///
+/// The game camera only needs the view direction from its focus, so every focus is placed at the
+/// same distance of the current one from `reference`. The path is rejected if any point is
+/// further than `safety_radius` from `origin`, where the camera was activated.
+pub fn import_path(
+    path: &Path,
+    reference: &CameraSnapshot,
+    origin: &glm::Vec3,
+    safety_radius: f32,
+) -> ImportResult<Vec<CameraSnapshot>> {
+    let focus_distance = glm::distance(&reference.pos, &reference.focus).max(1e-3);
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let points = match extension.as_str() {
+        "csv" => import_csv(path, reference.fov, focus_distance)?,
+        "gltf" | "glb" => import_gltf(path, reference.fov, focus_distance)?,
+        _ => return Err(format!("Unknown format: {:?}", path).into()),
+    };
+
+    validate(&points, origin, safety_radius)?;
+    Ok(points)
+}
+
+fn validate(points: &[CameraSnapshot], origin: &glm::Vec3, safety_radius: f32) -> ImportResult<()> {
+    if points.len() < 2 {
+        return Err("The path needs at least two points".into());
+    }
+
+    for (i, point) in points.iter().enumerate() {
+        if !point.is_valid() {
+            return Err(format!("Point {} has invalid values", i + 1).into());
+        }
+
+        let distance = calc_eucl_distance(origin, &point.pos);
+        if distance > safety_radius {
+            return Err(format!(
+                "Point {} is {:.1} units away from where the camera was activated, the limit is {}",
+                i + 1,
+                distance,
+                safety_radius
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Keyframe looking towards `forward`, with its focus `focus_distance` away.
+fn snapshot(
+    pos: glm::Vec3,
+    forward: glm::Vec3,
+    up: glm::Vec3,
+    fov: f32,
+    focus_distance: f32,
+) -> CameraSnapshot {
+    CameraSnapshot {
+        pos,
+        focus: pos + glm::normalize(&forward) * focus_distance,
+        rot: glm::normalize(&up),
+        fov,
+        duration: None,
+        hold: 0.,
+        easing: None,
+    }
+}
+
+/// The time between the keyframes becomes the duration of every segment.
+fn set_durations(points: &mut [CameraSnapshot], times: &[f32]) -> ImportResult<()> {
+    for i in 1..points.len() {
+        let duration = times[i] - times[i - 1];
+        if !duration.is_finite() || duration <= 0. {
+            return Err(format!("The time of point {} doesn't increase", i + 1).into());
+        }
+        points[i].duration = Some(duration);
+    }
+
+    Ok(())
+}
+
+/// Reads a CSV with a header row, where the columns are found by name. It needs `pos_*` and
+/// `target_*`, and optionally `up_*`, `time` (seconds) and `fov` (vertical, in radians) or
+/// `focal_length` (millimeters).
+fn import_csv(
+    path: &Path,
+    default_fov: f32,
+    focus_distance: f32,
+) -> ImportResult<Vec<CameraSnapshot>> {
+    let content = std::fs::read_to_string(path)?;
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+
+    let header: Vec<String> = lines
+        .next()
+        .ok_or("The file is empty")?
+        .split(',')
+        .map(|c| c.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|c| c == name);
+    let vector = |name: &str| -> Option<[usize; 3]> {
+        Some([
+            column(&format!("{}_x", name))?,
+            column(&format!("{}_y", name))?,
+            column(&format!("{}_z", name))?,
+        ])
+    };
+
+    let pos_columns = vector("pos").ok_or("Missing pos_x, pos_y or pos_z columns")?;
+    let target_columns =
+        vector("target").ok_or("Missing target_x, target_y or target_z columns")?;
+    let up_columns = vector("up");
+    let time_column = column("time");
+    let fov_column = column("fov");
+    let focal_length_column = column("focal_length");
+
+    let mut points = Vec::new();
+    let mut times = Vec::new();
+    for (row, line) in lines.enumerate() {
+        let values = line
+            .split(',')
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|e| format!("Row {}: {}", row + 1, e))?;
+        let get = |i: usize| {
+            values
+                .get(i)
+                .copied()
+                .ok_or_else(|| format!("Row {} is missing columns", row + 1))
+        };
+        let get_vector = |c: [usize; 3]| -> Result<glm::Vec3, String> {
+            Ok(from_blender(&glm::vec3(get(c[0])?, get(c[1])?, get(c[2])?)))
+        };
+
+        let pos = get_vector(pos_columns)?;
+        let target = get_vector(target_columns)?;
+        let up = match up_columns {
+            Some(c) => get_vector(c)?,
+            None => glm::vec3(0., 1., 0.),
+        };
+        let fov = match (fov_column, focal_length_column) {
+            (Some(c), _) => get(c)?,
+            (None, Some(c)) => focal_length_to_fov(get(c)?),
+            (None, None) => default_fov,
+        };
+        if let Some(c) = time_column {
+            times.push(get(c)?);
+        }
+
+        points.push(snapshot(pos, target - pos, up, fov, focus_distance));
+    }
+
+    if time_column.is_some() {
+        set_durations(&mut points, &times)?;
+    }
+
+    Ok(points)
+}
+
+/// glTF files in a `.gltf` (with its buffers in separate files) or `.glb` container.
+struct Gltf {
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Gltf {
+    fn open(path: &Path) -> ImportResult<Self> {
+        let data = std::fs::read(path)?;
+
+        // Binary glTF: a header followed by a JSON chunk and an optional binary chunk.
+        if data.starts_with(b"glTF") {
+            let read_u32 = |offset: usize| -> ImportResult<u32> {
+                let bytes = data.get(offset..offset + 4).ok_or("Truncated glb file")?;
+                Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            };
+
+            let mut json = None;
+            let mut buffers = Vec::new();
+            let mut offset = 12;
+            while offset + 8 <= data.len() {
+                let length = read_u32(offset)? as usize;
+                let kind = read_u32(offset + 4)?;
+                let chunk = data
+                    .get(offset + 8..offset + 8 + length)
+                    .ok_or("Truncated glb file")?;
+                match kind {
+                    0x4E4F534A => json = Some(serde_json::from_slice(chunk)?),
+                    0x004E4942 => buffers.push(chunk.to_vec()),
+                    _ => {}
+                }
+                offset += 8 + length;
+            }
+
+            return Ok(Gltf {
+                json: json.ok_or("The glb file doesn't have a JSON chunk")?,
+                buffers,
+            });
+        }
+
+        let json: Value = serde_json::from_slice(&data)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut buffers = Vec::new();
+        for buffer in json["buffers"].as_array().into_iter().flatten() {
+            let uri = buffer["uri"].as_str().ok_or("Buffer without uri")?;
+            if uri.starts_with("data:") {
+                return Err(
+                    "Embedded buffers aren't supported, use separate or binary glTF".into(),
+                );
+            }
+            buffers.push(std::fs::read(dir.join(uri))?);
+        }
+
+        Ok(Gltf { json, buffers })
+    }
+
+    /// Reads a float accessor as a list of elements with `components` floats each.
+    fn accessor(&self, index: &Value, components: usize) -> ImportResult<Vec<Vec<f32>>> {
+        let index = index.as_u64().ok_or("Invalid accessor index")? as usize;
+        let accessor = &self.json["accessors"][index];
+        if accessor["componentType"].as_u64() != Some(5126) {
+            return Err(format!("Accessor {} isn't made of floats", index).into());
+        }
+
+        let view = &self.json["bufferViews"][accessor["bufferView"]
+            .as_u64()
+            .ok_or("Accessor without buffer view")?
+            as usize];
+        let buffer = self
+            .buffers
+            .get(view["buffer"].as_u64().unwrap_or(0) as usize)
+            .ok_or("Missing buffer")?;
+
+        let count = accessor["count"].as_u64().ok_or("Accessor without count")? as usize;
+        let stride = view["byteStride"].as_u64().unwrap_or(components as u64 * 4) as usize;
+        let start = (view["byteOffset"].as_u64().unwrap_or(0)
+            + accessor["byteOffset"].as_u64().unwrap_or(0)) as usize;
+
+        (0..count)
+            .map(|i| {
+                (0..components)
+                    .map(|c| {
+                        let offset = start + i * stride + c * 4;
+                        let bytes = buffer
+                            .get(offset..offset + 4)
+                            .ok_or("Accessor out of the buffer bounds")?;
+                        Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Keyframes of one animated property.
+struct Track {
+    times: Vec<f32>,
+    values: Vec<Vec<f32>>,
+}
+
+impl Track {
+    fn read(gltf: &Gltf, sampler: &Value, components: usize) -> ImportResult<Self> {
+        let times = gltf
+            .accessor(&sampler["input"], 1)?
+            .into_iter()
+            .map(|t| t[0])
+            .collect::<Vec<_>>();
+        let mut values = gltf.accessor(&sampler["output"], components)?;
+
+        // Cubic splines store an in-tangent, the value and an out-tangent per keyframe.
+        if sampler["interpolation"].as_str() == Some("CUBICSPLINE") {
+            values = values.into_iter().skip(1).step_by(3).collect();
+        }
+
+        if times.is_empty() || times.len() != values.len() {
+            return Err("Animation sampler with mismatched keyframes".into());
+        }
+
+        Ok(Track { times, values })
+    }
+
+    /// Linearly interpolated value at `time`.
+    fn at(&self, time: f32) -> Vec<f32> {
+        let next = self.times.iter().position(|&t| t > time);
+        match next {
+            Some(0) => self.values[0].clone(),
+            None => self.values[self.values.len() - 1].clone(),
+            Some(i) => {
+                let (t0, t1) = (self.times[i - 1], self.times[i]);
+                let t = (time - t0) / (t1 - t0);
+                self.values[i - 1]
+                    .iter()
+                    .zip(&self.values[i])
+                    .map(|(a, b)| a + (b - a) * t)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Reads the first camera of a glTF file and its animation. glTF is Y up like the game, so only
+/// the view direction needs to be flipped, since its cameras look towards -Z.
+fn import_gltf(
+    path: &Path,
+    default_fov: f32,
+    focus_distance: f32,
+) -> ImportResult<Vec<CameraSnapshot>> {
+    let gltf = Gltf::open(path)?;
+    let json = &gltf.json;
+
+    let nodes = json["nodes"]
+        .as_array()
+        .ok_or("The file doesn't have nodes")?;
+    let node_index = nodes
+        .iter()
+        .position(|n| n.get("camera").is_some())
+        .ok_or("The file doesn't have a camera")?;
+    let node = &nodes[node_index];
+    let camera_index = node["camera"].as_u64().unwrap_or(0);
+    let fov_pointer = format!("/cameras/{}/perspective/yfov", camera_index);
+
+    let vector = |value: &Value, default: &[f32]| -> Vec<f32> {
+        value
+            .as_array()
+            .map(|a| a.iter().map(|v| v.as_f64().unwrap_or(0.) as f32).collect())
+            .unwrap_or_else(|| default.to_vec())
+    };
+    let static_fov = json["cameras"][camera_index as usize]["perspective"]["yfov"]
+        .as_f64()
+        .map(|f| f as f32)
+        .unwrap_or(default_fov);
+
+    let mut translation = Track {
+        times: vec![0.],
+        values: vec![vector(&node["translation"], &[0., 0., 0.])],
+    };
+    let mut rotation = Track {
+        times: vec![0.],
+        values: vec![vector(&node["rotation"], &[0., 0., 0., 1.])],
+    };
+    let mut fov = Track {
+        times: vec![0.],
+        values: vec![vec![static_fov]],
+    };
+    let mut animated = false;
+
+    // Only the first animation that moves the camera is used.
+    for animation in json["animations"].as_array().into_iter().flatten() {
+        let samplers = &animation["samplers"];
+        for channel in animation["channels"].as_array().into_iter().flatten() {
+            let target = &channel["target"];
+            let sampler = &samplers[channel["sampler"].as_u64().unwrap_or(0) as usize];
+            let pointer = target["extensions"]["KHR_animation_pointer"]["pointer"].as_str();
+
+            if target["node"].as_u64() == Some(node_index as u64) {
+                match target["path"].as_str() {
+                    Some("translation") => translation = Track::read(&gltf, sampler, 3)?,
+                    Some("rotation") => rotation = Track::read(&gltf, sampler, 4)?,
+                    _ => continue,
+                }
+            } else if pointer == Some(fov_pointer.as_str()) {
+                fov = Track::read(&gltf, sampler, 1)?;
+            } else {
+                continue;
+            }
+            animated = true;
+        }
+
+        if animated {
+            break;
+        }
+    }
+
+    // The keyframes follow the track with the most of them.
+    let times = [&translation, &rotation, &fov]
+        .iter()
+        .max_by_key(|track| track.times.len())
+        .map(|track| track.times.clone())
+        .unwrap_or_default();
+
+    let mut points: Vec<CameraSnapshot> = times
+        .iter()
+        .map(|&time| {
+            let p = translation.at(time);
+            let r = rotation.at(time);
+            let q = glm::quat_normalize(&glm::quat(r[0], r[1], r[2], r[3]));
+            let (backward, up) = orientation::from_quat(&q);
+
+            snapshot(
+                glm::vec3(p[0], p[1], p[2]),
+                -backward,
+                up,
+                fov.at(time)[0],
+                focus_distance,
+            )
+        })
+        .collect();
+    set_durations(&mut points, &times)?;
+
+    Ok(points)
+}
@@ -0,0 +1,89 @@
+use crate::config::InertiaConfig;
+use crate::utils::Input;
+
+/// Velocity that follows the one requested by the controls, accelerating towards it and
+/// gliding to a stop when the controls are released.
+#[derive(Default, Debug, Clone, Copy)]
+struct Damped {
+    velocity: f32,
+}
+
+impl Damped {
+    fn update(&mut self, target: f32, half_life: f32, dt: f32) -> f32 {
+        if half_life <= 0. {
+            self.velocity = target;
+        } else {
+            let t = 1. - 0.5_f32.powf(dt / half_life);
+            self.velocity += (target - self.velocity) * t;
+        }
+
+        self.velocity
+    }
+}
+
+/// Smooths the speeds of the camera so it starts and stops gradually.
+#[derive(Default, Debug)]
+pub struct Inertia {
+    pub enabled: bool,
+    config: InertiaConfig,
+    pos: (Damped, Damped),
+    altitude: Damped,
+    focus: (Damped, Damped),
+    roll: Damped,
+    fov: Damped,
+}
+
+impl Inertia {
+    pub fn new(config: InertiaConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            config,
+            ..Inertia::default()
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.stop();
+    }
+
+    /// Drops the current velocity, e.g. when the controls are taken by something else.
+    pub fn stop(&mut self) {
+        *self = Inertia::new(InertiaConfig {
+            enabled: self.enabled,
+            ..self.config
+        });
+    }
+
+    /// Replaces the speeds of `input` by the smoothed ones and applies the rotation and fov
+    /// speeds. Without inertia the speeds are used as they are.
+    pub fn update(&mut self, input: &mut Input) {
+        let dt = input.dt;
+        let c = if self.enabled {
+            self.config
+        } else {
+            InertiaConfig {
+                position_half_life: 0.,
+                look_half_life: 0.,
+                roll_half_life: 0.,
+                fov_half_life: 0.,
+                ..self.config
+            }
+        };
+
+        let position = c.position_half_life;
+        input.delta_pos.0 = self.pos.0.update(input.delta_pos.0, position, dt);
+        input.delta_pos.1 = self.pos.1.update(input.delta_pos.1, position, dt);
+        input.delta_altitude = self.altitude.update(input.delta_altitude, position, dt);
+
+        let look = c.look_half_life;
+        input.delta_focus.0 = self.focus.0.update(input.delta_focus.0, look, dt);
+        input.delta_focus.1 = self.focus.1.update(input.delta_focus.1, look, dt);
+
+        input.delta_roll = self.roll.update(input.delta_roll, c.roll_half_life, dt);
+        input.delta_fov = self.fov.update(input.delta_fov, c.fov_half_life, dt);
+
+        input.roll += input.delta_roll * dt;
+        input.fov += input.delta_fov * dt;
+    }
+}
@@ -30,6 +30,35 @@ asm_get_camera_data PROC
     jmp [g_get_camera_data]
 asm_get_camera_data ENDP
 
+; Same as asm_get_camera_data for CPUs without movbe, where Cemu swaps the
+; bytes of the register before a regular mov.
+asm_get_camera_data_bswap PROC
+    pushf
+
+    ; Steal the camera pointer
+    push rbx
+    lea rbx, [r13 + rdx + 654h]
+    sub rbx, 24h
+    mov [g_camera_struct], rbx
+    pop rbx
+
+    ; The register is swapped even when the write is skipped, like the original
+    ; code leaves it.
+    bswap r14d
+
+    cmp g_camera_active, 0
+    je original_bswap
+    jmp ending_bswap
+
+    original_bswap:
+    mov [r13 + rdx + 654h], r14d
+    cvtss2sd xmm0, xmm0
+
+    ending_bswap:
+    popf
+    jmp [g_get_camera_data]
+asm_get_camera_data_bswap ENDP
+
 ; HACK: We use an intermediary to replace the function pointer in rax since we
 ; still don't write a function trampoline because life
 asm_override_xinput_call PROC
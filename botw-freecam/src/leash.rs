@@ -0,0 +1,72 @@
+use crate::camera::GameCamera;
+use crate::config::{CameraConfig, LeashMode};
+use log::*;
+use nalgebra_glm as glm;
+
+/// Fraction of the safety radius where the console starts warning.
+const WARNING_DISTANCE: f32 = 0.9;
+
+/// Keeps the camera close to where it was activated, since the game crashes when it goes too
+/// far from the player.
+#[derive(Debug)]
+pub struct Leash {
+    pub mode: LeashMode,
+    pub radius: f32,
+    // Whether the camera was close to the edge or past it on the previous check, so the
+    // warnings aren't repeated every tick.
+    near: bool,
+    outside: bool,
+}
+
+impl Leash {
+    pub fn new(config: &CameraConfig) -> Self {
+        Self {
+            mode: config.leash,
+            radius: config.safety_radius,
+            near: false,
+            outside: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.near = false;
+        self.outside = false;
+    }
+
+    /// Checks the distance from the camera to `origin`, clamping the camera if needed. Returns
+    /// `true` when the sequence has to be cleared.
+    pub fn check(&mut self, gc: &mut GameCamera, origin: &glm::Vec3) -> bool {
+        if self.mode == LeashMode::Off {
+            return false;
+        }
+
+        let distance = glm::distance(&glm::Vec3::from(gc.pos), origin);
+        let near = distance > self.radius * WARNING_DISTANCE;
+        if near && !self.near {
+            warn!(
+                "The camera is {:.0} units away from where it was activated, the limit is {:.0}",
+                distance, self.radius
+            );
+        }
+        self.near = near;
+
+        let outside = distance > self.radius;
+        let crossed = outside && !self.outside;
+        self.outside = outside;
+
+        match self.mode {
+            LeashMode::Off => false,
+            LeashMode::Warn => {
+                if crossed {
+                    warn!("The camera is past the safety radius, the game could crash");
+                }
+                false
+            }
+            LeashMode::Clamp => {
+                gc.clamp_distance(origin, self.radius);
+                false
+            }
+            LeashMode::ClearSequence => crossed,
+        }
+    }
+}
@@ -1,29 +1,62 @@
+// The freecam only runs inside of Cemu on Windows, but everything that doesn't touch the game
+// builds on any platform so it can be tested.
+#![cfg_attr(not(windows), allow(dead_code, unused_imports))]
+
+#[cfg(windows)]
 use memory_rs::internal::{
     injections::{Detour, Inject, Injection},
     memory::resolve_module_path,
     process_info::ProcessInfo,
 };
 use std::ffi::CString;
+#[cfg(windows)]
 use winapi::um::consoleapi::AllocConsole;
+#[cfg(windows)]
 use winapi::um::libloaderapi::{FreeLibraryAndExitThread, GetModuleHandleA};
+#[cfg(windows)]
 use winapi::um::wincon::FreeConsole;
+#[cfg(windows)]
 use winapi::um::winuser;
-use winapi::um::xinput;
+#[cfg(windows)]
 use winapi::{shared::minwindef::LPVOID, um::libloaderapi::GetProcAddress};
 
 use log::*;
+use nalgebra_glm as glm;
 use simplelog::*;
 
+mod backend;
 mod camera;
+mod config;
 mod dolly;
+mod easing;
+mod export;
+#[cfg(windows)]
 mod globals;
+mod import;
+mod inertia;
+mod leash;
+mod orientation;
+mod profiles;
+mod scanner;
+mod sequence;
+mod spline;
 mod utils;
 
+#[cfg(windows)]
+use backend::WindowsInput;
 use camera::*;
+use config::{Action, FreecamConfig};
 use dolly::*;
+#[cfg(windows)]
 use globals::*;
-use utils::{check_key_press, error_message, handle_keyboard, Input, Keys};
-
+use inertia::Inertia;
+use leash::Leash;
+use profiles::{CameraOffsets, Interceptor, Profile};
+#[cfg(windows)]
+use utils::error_message;
+use utils::{handle_keyboard, Input, Keys};
+
+use std::f32::consts::FRAC_PI_2;
 use std::io::{self, Write};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
@@ -34,6 +67,7 @@ fn write_red(msg: &str) -> io::Result<()> {
     stdout.reset()
 }
 
+#[cfg(windows)]
 unsafe extern "system" fn wrapper(lib: LPVOID) -> u32 {
     AllocConsole();
     {
@@ -68,13 +102,16 @@ unsafe extern "system" fn wrapper(lib: LPVOID) -> u32 {
     0
 }
 
-#[derive(Debug)]
-struct CameraOffsets {
-    camera: usize,
-    rotation_vec1: usize,
-}
+/// Time between the messages that tell we're still waiting for the game.
+const WAIT_STATUS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
 
-fn get_camera_function() -> Result<CameraOffsets, Box<dyn std::error::Error>> {
+/// Finds the camera code of the first profile that matches the game, once it's recompiled. Returns
+/// `None` if HOME is pressed while waiting.
+#[cfg(windows)]
+fn get_camera_function(
+    profiles: &[Profile],
+    timeout: Option<std::time::Duration>,
+) -> Result<Option<CameraOffsets>, Box<dyn std::error::Error>> {
     let function_name = CString::new("PPCRecompiler_getJumpTableBase").unwrap();
     let proc_handle = unsafe { GetModuleHandleA(std::ptr::null_mut()) };
     let func = unsafe { GetProcAddress(proc_handle, function_name.as_ptr()) };
@@ -93,48 +130,91 @@ fn get_camera_function() -> Result<CameraOffsets, Box<dyn std::error::Error>> {
         );
     }
 
-    let array = unsafe { std::slice::from_raw_parts(addr as *const usize, 0x8800000 / 0x8) };
-    let original_bytes = [
-        0x45_u8, 0x0F, 0x38, 0xF1, 0xB4, 0x15, 0x54, 0x06, 0x00, 0x00,
-    ];
+    let array =
+        unsafe { std::slice::from_raw_parts(addr as *const usize, profiles::JUMP_TABLE_LEN) };
 
     // As Exzap said, "It will only compile it once its executed. Before that the table points to a placeholder function"
     // So we'll wait until the game is in the world and the code will be recompiled, then the pointer should be changed to the right function.
     // Once is resolved, we can lookup the rest of the functions since the camera we assume the camera is active
-    let dummy_pointer = array[0];
-    info!("Waiting for the game to start");
-    let camera_offset = loop {
-        let function_start = array[0x2C085FC / 4];
+    info!("Waiting for the game to start, load a save to continue. Press HOME to cancel");
+    let start = std::time::Instant::now();
+    let mut last_status = start;
+    let compiled = loop {
+        let compiled: Vec<&Profile> = profiles.iter().filter(|p| p.is_compiled(array)).collect();
 
-        if dummy_pointer != function_start {
+        if !compiled.is_empty() {
             info!("Pointer found");
-            break function_start + 0x7E;
+            break compiled;
+        }
+
+        if utils::check_key_press(winuser::VK_HOME) {
+            info!("Cancelled while waiting for the game");
+            return Ok(None);
+        }
+
+        let waited = start.elapsed();
+        if matches!(timeout, Some(timeout) if waited >= timeout) {
+            return Err(format!(
+                "The camera function wasn't recompiled after {} seconds. Make sure the game is \
+                in the world, or increase `startup.timeout` in {}",
+                waited.as_secs(),
+                config::CONFIG_FILE
+            )
+            .into());
         }
-        std::thread::sleep(std::time::Duration::from_secs(1))
+
+        if last_status.elapsed() >= WAIT_STATUS_INTERVAL {
+            last_status = std::time::Instant::now();
+            let functions: Vec<String> = profiles
+                .iter()
+                .map(|p| format!("{:#x} ({})", p.camera.address, p.name))
+                .collect();
+            info!(
+                "Still waiting after {}s for Cemu to recompile the camera function {}. It happens \
+                once the game is in the world. Press HOME to cancel",
+                waited.as_secs(),
+                functions.join(" or ")
+            );
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(100))
     };
 
-    let camera_bytes = unsafe { std::slice::from_raw_parts((camera_offset) as *const u8, 10) };
-    if camera_bytes != original_bytes {
-        return Err(format!(
-            "Function signature doesn't match, This can mean two things:\n\n\
-            * You're using a cheat that requires a 'master cheat' to be activated (which\
-              effectively removes `movbe`)
-            * You're using a pre 2016 CPU (your cpu doesn't support `movbe`)\n\
-            * You're not using the version described on the README.md\n\
-            {:x?} != {:x?}",
-            camera_bytes, original_bytes
-        )
-        .into());
+    let mut errors = vec![];
+    for profile in compiled {
+        match profile.resolve(array) {
+            Ok(offsets) => {
+                info!(
+                    "Using profile {} (game v{} {:?}, Cemu {})",
+                    profile.name, profile.game_version, profile.title_ids, profile.cemu_version
+                );
+                return Ok(Some(offsets));
+            }
+            Err(e) => errors.push(format!("{}: {}", profile.name, e)),
+        }
     }
 
-    let rotation_vec1 = array[0x2e57fdc / 4] + 0x57;
+    let cpu = if profiles::cpu_has_movbe() {
+        "supports"
+    } else {
+        "doesn't support"
+    };
 
-    Ok(CameraOffsets {
-        camera: camera_offset,
-        rotation_vec1,
-    })
+    Err(format!(
+        "Function signature doesn't match, This can mean three things:\n\n\
+        * You're using a cheat that requires a 'master cheat' to be activated (which \
+          effectively removes `movbe`) and there's no `bswap` profile for your version\n\
+        * You're using a CPU without `movbe` (yours {} it) and there's no `bswap` profile for \
+          your version\n\
+        * You're not using the version described on the README.md\n\
+        {}",
+        cpu,
+        errors.join("\n")
+    )
+    .into())
 }
 
+#[cfg(windows)]
 fn block_xinput(proc_inf: &ProcessInfo) -> Result<Detour, Box<dyn std::error::Error>> {
     // Find input blocker for xinput only
 
@@ -153,77 +233,142 @@ fn block_xinput(proc_inf: &ProcessInfo) -> Result<Detour, Box<dyn std::error::Er
     Ok(injection)
 }
 
-fn patch(_lib: LPVOID) -> Result<(), Box<dyn std::error::Error>> {
+#[cfg(windows)]
+fn patch(lib: LPVOID) -> Result<(), Box<dyn std::error::Error>> {
     info!(
         "Breath of the Wild freecam by @etra0, v{}",
         utils::get_version()
     );
     write_red("If you close this window the game will close. Use HOME to deattach the freecamera (will close this window as well).")?;
-    println!("{}", utils::INSTRUCTIONS);
-    write_red("Controller input will only be detected if Xinput is used in the Control settings, otherwise use the keyboard.")?;
-    let proc_inf = ProcessInfo::new(None)?;
+
+    let mut config_path = resolve_module_path(lib).unwrap();
+    config_path.push(config::CONFIG_FILE);
+    let config = FreecamConfig::load(&config_path).unwrap_or_else(|e| {
+        error!("Couldn't load the config, using the default one: {}", e);
+        FreecamConfig::default()
+    });
 
     let mut input = Input::new();
+    input.bindings = config.bindings()?;
+    input.max_pitch = config.camera.max_pitch.to_radians();
+    let mut inertia = Inertia::new(config.inertia);
+    let mut leash = Leash::new(&config.camera);
+
+    println!("{}", utils::instructions(&input.bindings));
+    write_red("Controller input will only be detected if Xinput is used in the Control settings, otherwise use the keyboard.")?;
+    let proc_inf = ProcessInfo::new(None)?;
 
     let mut active = false;
 
-    let mut points: Vec<CameraSnapshot> = vec![];
+    let mut sequence = Sequence::default();
+
+    // Point of the sequence being edited.
+    let mut selected: Option<usize> = None;
+
+    // Sequence currently playing, advanced once per tick.
+    let mut playback: Option<Playback> = None;
+    let mut last_tick = std::time::Instant::now();
+
+    // Point captured to orbit around or look at, and the ones being used.
+    let mut target: Option<glm::Vec3> = None;
+    let mut orbit: Option<glm::Vec3> = None;
+    let mut look_at: Option<glm::Vec3> = None;
 
     // This variable will hold the initial position when the freecamera is activated.
     let mut starting_point: Option<CameraSnapshot> = None;
 
-    let camera_struct = get_camera_function()?;
+    // Saved sequences live next to the log file.
+    let mut sequences_dir = resolve_module_path(lib).unwrap();
+    sequences_dir.push("sequences");
+    let mut exports_dir = resolve_module_path(lib).unwrap();
+    exports_dir.push("exports");
+    let mut imports_dir = resolve_module_path(lib).unwrap();
+    imports_dir.push("imports");
+
+    // Profiles next to the dll are tried before the ones that come with the freecam, so they
+    // can replace them.
+    let mut profiles_dir = resolve_module_path(lib).unwrap();
+    profiles_dir.push("profiles");
+    let mut profiles = vec![];
+    for path in profiles::profile_paths(&profiles_dir) {
+        match profiles::load_profile(&path) {
+            Ok(profile) => {
+                info!("Loaded profile {} from {:?}", profile.name, path);
+                profiles.push(profile);
+            }
+            Err(e) => {
+                error!("{}", e);
+                error_message(&e);
+            }
+        }
+    }
+
+    for profile in profiles::builtin_profiles() {
+        profile.validate()?;
+        profiles.push(profile);
+    }
+
+    // The profiles for the code Cemu emits on this CPU are tried first.
+    info!("Trying the {:?} profiles first", Interceptor::for_cpu());
+    profiles::prefer_interceptor(&mut profiles, Interceptor::for_cpu());
+
+    let timeout = match config.startup.timeout {
+        0 => None,
+        seconds => Some(std::time::Duration::from_secs(seconds)),
+    };
+    let camera_struct = match get_camera_function(&profiles, timeout)? {
+        Some(camera_struct) => camera_struct,
+        None => return Ok(()),
+    };
     info!("{:x?}", camera_struct);
     let camera_pointer = camera_struct.camera;
     info!("Camera function camera_pointer: {:x}", camera_pointer);
 
     block_xinput(&proc_inf)?;
 
+    let interceptor = match camera_struct.interceptor {
+        Interceptor::Movbe => unsafe { &asm_get_camera_data as *const u8 as usize },
+        Interceptor::Bswap => unsafe { &asm_get_camera_data_bswap as *const u8 as usize },
+    };
+
     let mut cam = unsafe {
         Detour::new(
             camera_pointer,
-            14,
-            &asm_get_camera_data as *const u8 as usize,
+            camera_struct.interceptor.detour_len(),
+            interceptor,
             Some(&mut g_get_camera_data),
         )
     };
 
-    let mut nops: Vec<Box<dyn Inject>> = vec![
-        // Camera pos and focus writers
-        Box::new(Injection::new(camera_struct.camera + 0x17, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0x55, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0xC2, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0xD9, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0x117, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0x12E, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0x15D, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0x174, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0x22A, vec![0x90; 10])),
-        Box::new(Injection::new(camera_struct.camera + 0x22A, vec![0x90; 10])),
-
-        // Rotation
-        Box::new(Injection::new(camera_struct.rotation_vec1, vec![0x90; 7])),
-        Box::new(Injection::new(camera_struct.rotation_vec1 + 0x14, vec![0x90; 7])),
-        Box::new(Injection::new(camera_struct.rotation_vec1 + 0x28, vec![0x90; 7])),
-        Box::new(block_xinput(&proc_inf)?),
-    ];
+    let mut nops: Vec<Box<dyn Inject>> = camera_struct
+        .nops
+        .iter()
+        .map(|&(addr, len)| Box::new(Injection::new(addr, vec![0x90; len])) as Box<dyn Inject>)
+        .collect();
+    // Unlocking the character removes the last injection.
+    nops.push(Box::new(block_xinput(&proc_inf)?));
 
     cam.inject();
 
-    let xinput_func =
-        |a: u32, b: &mut xinput::XINPUT_STATE| -> u32 { unsafe { xinput::XInputGetState(a, b) } };
+    let input_source = WindowsInput;
 
     loop {
-        utils::handle_controller(&mut input, xinput_func);
-        handle_keyboard(&mut input);
+        let dt = last_tick.elapsed().as_secs_f32();
+        last_tick = std::time::Instant::now();
+        input.dt = dt.min(utils::MAX_TICK);
+
+        utils::handle_controller(&mut input, &input_source);
+        handle_keyboard(&mut input, &input_source);
+        inertia.update(&mut input);
         input.sanitize();
 
-        if input.deattach || check_key_press(winuser::VK_HOME) {
+        if input.deattach || input.keys.held(winuser::VK_HOME) {
             info!("Exiting");
             break;
         }
 
         input.is_active = active;
+        input.is_playing = playback.is_some();
         if input.change_active {
             active = !active;
 
@@ -238,6 +383,10 @@ fn patch(_lib: LPVOID) -> Result<(), Box<dyn std::error::Error>> {
             } else {
                 nops.iter_mut().remove_injection();
                 starting_point = None;
+                playback = None;
+                orbit = None;
+                leash.reset();
+                look_at = None;
                 input.unlock_character = false;
             }
 
@@ -254,7 +403,8 @@ fn patch(_lib: LPVOID) -> Result<(), Box<dyn std::error::Error>> {
             let gc = (g_camera_struct as *mut GameCamera).as_mut().ok_or("GameCamera was still null")?;
             if !active {
                 input.fov = gc.fov.into();
-                input.delta_rotation = 0.;
+                input.roll = 0.;
+                inertia.stop();
                 continue;
             }
 
@@ -262,40 +412,336 @@ fn patch(_lib: LPVOID) -> Result<(), Box<dyn std::error::Error>> {
                 starting_point = Some(CameraSnapshot::new(gc));
             }
 
-            if !points.is_empty() {
-                let origin = gc.pos.into();
-                if utils::calc_eucl_distance(&origin, &points[0].pos) > 400. {
-                    warn!("Sequence cleaned to prevent game crashing");
-                    points.clear();
+            if input.reset_roll || input.snap_roll {
+                input.roll = if input.snap_roll {
+                    (input.roll / FRAC_PI_2).round() * FRAC_PI_2
+                } else {
+                    0.
+                };
+                inertia.stop();
+                info!("Rotation: {:.0}°", input.roll.to_degrees());
+            }
+
+            if let Some(pb) = playback.as_mut() {
+                let controls = &input.playback;
+
+                if controls.toggle_pause {
+                    pb.paused = !pb.paused;
+                    info!("Sequence paused: {}", pb.paused);
+                }
+
+                if controls.toggle_reverse {
+                    pb.reverse = !pb.reverse;
+                    info!("Sequence reversed: {}", pb.reverse);
                 }
+
+                if pb.paused {
+                    pb.step(controls.step);
+                } else {
+                    // A full sequence can be scrubbed in four seconds.
+                    pb.scrub(controls.scrub * dt * pb.total() / 4.);
+                }
+
+                if controls.delta_speed != 0. {
+                    pb.speed = (pb.speed + controls.delta_speed * dt).clamp(0.1, 4.);
+                    println!("Playback speed: {:.2}", pb.speed);
+                }
+
+                if let Some(keyframe) = controls.keyframe {
+                    pb.jump_to_keyframe(keyframe);
+                    info!("Jumped to point {}", keyframe + 1);
+                }
+
+                if controls.skip != 0 {
+                    let keyframe = pb.skip_keyframes(controls.skip);
+                    info!("Jumped to point {}", keyframe + 1);
+                }
+
+                if !pb.tick(gc, dt) || controls.stop {
+                    info!("Sequence finished");
+                    playback = None;
+                }
+
+                // The rest of the controls wait until the sequence finishes.
+                inertia.stop();
+                input.reset();
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                continue;
             }
 
-            if check_key_press(winuser::VK_F9) {
+            if input.held(Action::AddPoint) {
                 let cs = CameraSnapshot::new(gc);
                 info!("Point added to interpolation: {:?}", cs);
-                points.push(cs);
+                sequence.points.push(cs);
+                selected = Some(sequence.points.len() - 1);
                 std::thread::sleep(std::time::Duration::from_millis(400));
             }
 
-            if check_key_press(winuser::VK_F11) {
+            // The sequence could have been cleaned or loaded, keep the selection in bounds.
+            selected = match sequence.points.len() {
+                0 => None,
+                len => Some(selected.unwrap_or(len - 1).min(len - 1)),
+            };
+
+            if let Some(i) = selected {
+                let len = sequence.points.len();
+                let mut changed = true;
+
+                if input.just_pressed(Action::SelectNext) {
+                    selected = Some((i + 1) % len);
+                } else if input.just_pressed(Action::SelectPrevious) {
+                    selected = Some((i + len - 1) % len);
+                } else if input.just_pressed(Action::JumpToPoint) {
+                    let point = &sequence.points[i];
+                    point.set_inplace(gc);
+                    // consume_input rebuilds the fov and the rotation from the input
+                    input.fov = point.fov;
+                    input.roll =
+                        GameCamera::calculate_roll(point.focus, point.pos, point.rot);
+                    inertia.stop();
+                    info!("Camera moved to point {}", i + 1);
+                } else if input.just_pressed(Action::ReplacePoint) {
+                    let previous = &sequence.points[i];
+                    sequence.points[i] = CameraSnapshot {
+                        duration: previous.duration,
+                        hold: previous.hold,
+                        easing: previous.easing,
+                        ..CameraSnapshot::new(gc)
+                    };
+                    info!("Point {} replaced", i + 1);
+                } else if input.just_pressed(Action::InsertPoint) {
+                    sequence.points.insert(i + 1, CameraSnapshot::new(gc));
+                    selected = Some(i + 1);
+                    info!("Point inserted after point {}", i + 1);
+                } else if input.just_pressed(Action::DeletePoint) {
+                    sequence.points.remove(i);
+                    selected = match sequence.points.len() {
+                        0 => None,
+                        len => Some(i.min(len - 1)),
+                    };
+                    info!("Point {} deleted", i + 1);
+                } else if input.just_pressed(Action::MovePointLater) && i + 1 < len {
+                    sequence.points.swap(i, i + 1);
+                    selected = Some(i + 1);
+                } else if input.just_pressed(Action::MovePointEarlier) && i > 0 {
+                    sequence.points.swap(i, i - 1);
+                    selected = Some(i - 1);
+                } else {
+                    changed = false;
+                }
+
+                if changed {
+                    match selected {
+                        Some(i) => {
+                            println!("Selected point: {} of {}", i + 1, sequence.points.len())
+                        }
+                        None => println!("The sequence is empty"),
+                    }
+                }
+            }
+
+            if input.delta_keyframe_duration != 0. || input.delta_keyframe_hold != 0. {
+                let n_segments = sequence.points.len().max(2) - 1;
+                let default_duration = input.dolly_duration / (n_segments as f32);
+                if let Some(i) = selected {
+                    let point = &mut sequence.points[i];
+                    point.adjust_timing(
+                        default_duration,
+                        input.delta_keyframe_duration,
+                        input.delta_keyframe_hold,
+                    );
+                    println!(
+                        "Point {} duration: {:?}, hold: {}",
+                        i + 1,
+                        point.duration,
+                        point.hold
+                    );
+                }
+            }
+
+            if input.held(Action::ClearSequence) {
                 info!("Sequence cleaned!");
-                points.clear();
+                sequence.points.clear();
+                std::thread::sleep(std::time::Duration::from_millis(400));
+            }
+
+            let save_slot = input.held(Action::SaveSlot);
+            let load_slot = input.held(Action::LoadSlot);
+            if save_slot || load_slot {
+                for slot in 1..=9 {
+                    if !input.keys.held(Keys::Key0 as i32 + slot as i32) {
+                        continue;
+                    }
+
+                    let path = sequence::slot_path(&sequences_dir, slot);
+                    if save_slot {
+                        if sequence.points.is_empty() {
+                            warn!("There are no points to save");
+                        } else {
+                            match sequence::save_sequence(&path, &sequence) {
+                                Ok(_) => info!("Sequence saved to slot {} ({:?})", slot, path),
+                                Err(e) => error!("Couldn't save slot {}: {}", slot, e),
+                            }
+                        }
+                    } else {
+                        match sequence::load_sequence(&path) {
+                            Ok(loaded) => {
+                                info!(
+                                    "Sequence loaded from slot {} ({} points)",
+                                    slot,
+                                    loaded.points.len()
+                                );
+                                sequence = loaded;
+                            }
+                            Err(e) => error!("Couldn't load slot {}: {}", slot, e),
+                        }
+                    }
+
+                    std::thread::sleep(std::time::Duration::from_millis(400));
+                    break;
+                }
+            }
+
+            if input.held(Action::ToggleTiming) {
+                sequence.timing = match sequence.timing {
+                    Timing::Uniform => Timing::ConstantSpeed,
+                    Timing::ConstantSpeed => Timing::Uniform,
+                };
+                info!("Sequence timing: {:?}", sequence.timing);
                 std::thread::sleep(std::time::Duration::from_millis(400));
             }
 
-            if check_key_press(winuser::VK_F10) & (points.len() > 1) {
+            if input.held(Action::CycleSpline) {
+                sequence.spline = sequence.spline.next();
+                info!("Sequence spline: {:?}", sequence.spline);
+                std::thread::sleep(std::time::Duration::from_millis(400));
+            }
+
+            let cycle_point_easing = input.held(Action::CyclePointEasing);
+            if cycle_point_easing || input.held(Action::CycleEasing) {
+                if cycle_point_easing {
+                    if let Some(i) = selected {
+                        let point = &mut sequence.points[i];
+                        point.easing = match point.easing {
+                            None => Some(easing::Easing::Linear),
+                            Some(easing::Easing::CubicBezier { .. }) => None,
+                            Some(e) => Some(e.next()),
+                        };
+                        info!("Point {} easing: {:?}", i + 1, point.easing);
+                    }
+                } else {
+                    sequence.easing = sequence.easing.next();
+                    info!("Sequence easing: {:?}", sequence.easing);
+                }
+                std::thread::sleep(std::time::Duration::from_millis(400));
+            }
+
+            if input.just_pressed(Action::Export) {
                 let dur = std::time::Duration::from_secs_f32(input.dolly_duration);
-                points.interpolate(gc, dur, false);
-                std::thread::sleep(std::time::Duration::from_millis(500));
+                match export::export_sequence(&exports_dir, "sequence", &sequence, dur) {
+                    Ok(_) => info!("Sequence exported to {:?}", exports_dir),
+                    Err(e) => error!("Couldn't export the sequence: {}", e),
+                }
+            }
+
+            if input.just_pressed(Action::Import) {
+                let path = ["sequence.gltf", "sequence.glb", "sequence.csv"]
+                    .iter()
+                    .map(|name| imports_dir.join(name))
+                    .find(|path| path.exists());
+
+                // The view comes from the current camera, but the safety radius is measured
+                // from where it was activated, like the leash does.
+                let reference = CameraSnapshot::new(gc);
+                let origin = starting_point
+                    .as_ref()
+                    .map_or(reference.pos, |start| start.pos);
+
+                match path {
+                    Some(path) => match import::import_path(
+                        &path,
+                        &reference,
+                        &origin,
+                        leash.radius,
+                    ) {
+                        Ok(points) => {
+                            info!("Imported {} points from {:?}", points.len(), path);
+                            sequence = Sequence {
+                                points,
+                                ..Sequence::default()
+                            };
+                            selected = None;
+                        }
+                        Err(e) => error!("Couldn't import {:?}: {}", path, e),
+                    },
+                    None => warn!(
+                        "There's no sequence.gltf, .glb or .csv in {:?}",
+                        imports_dir
+                    ),
+                }
             }
 
-            if check_key_press(Keys::L as _) & (points.len() > 1) {
+            let play = input.just_pressed(Action::Play);
+            let play_loop = input.just_pressed(Action::PlayLoop);
+            if (play || play_loop) && (sequence.points.len() > 1) {
                 let dur = std::time::Duration::from_secs_f32(input.dolly_duration);
-                points.interpolate(gc, dur, true);
-                std::thread::sleep(std::time::Duration::from_millis(500));
+                playback = Some(Playback::new(&sequence, dur, play_loop));
+                info!("Playing sequence");
+            }
+
+            if input.just_pressed(Action::ToggleInertia) {
+                inertia.toggle();
+                info!("Inertia: {}", inertia.enabled);
+            }
+
+            if input.just_pressed(Action::CaptureTarget) {
+                let pos = gc.pos.into();
+                info!("Target captured at {:?}", pos);
+                target = Some(pos);
+            }
+
+            if input.just_pressed(Action::ToggleOrbit) {
+                orbit = match orbit {
+                    Some(_) => None,
+                    None => Some(target.unwrap_or_else(|| gc.focus.into())),
+                };
+                match orbit {
+                    Some(center) => info!("Orbiting around {:?}", center),
+                    None => info!("Orbit disabled"),
+                }
+            }
+
+            if input.just_pressed(Action::ToggleLookAt) {
+                look_at = match (look_at, target) {
+                    (Some(_), _) => None,
+                    (None, Some(target)) => Some(target),
+                    (None, None) => {
+                        warn!("Capture a target before locking the view on it");
+                        None
+                    }
+                };
+                info!("Look-at lock: {}", look_at.is_some());
             }
 
-            if check_key_press(winuser::VK_F7) {
+            if input.just_pressed(Action::ToggleSequenceLookAt) {
+                sequence.look_at = match (sequence.look_at, target) {
+                    (Some(_), _) => None,
+                    (None, Some(target)) => Some(target),
+                    (None, None) => {
+                        warn!("Capture a target before making the sequence look at it");
+                        None
+                    }
+                };
+                info!("Sequence looks at: {:?}", sequence.look_at);
+            }
+
+            if input.just_pressed(Action::CycleLeash) {
+                leash.mode = leash.mode.next();
+                leash.reset();
+                info!("Safety radius: {:?}", leash.mode);
+            }
+
+            if input.held(Action::UnlockCharacter) {
                 input.unlock_character = !input.unlock_character;
                 if input.unlock_character {
                     nops.last_mut().unwrap().remove_injection();
@@ -306,9 +752,22 @@ fn patch(_lib: LPVOID) -> Result<(), Box<dyn std::error::Error>> {
                 std::thread::sleep(std::time::Duration::from_millis(500));
             }
 
-            if !input.unlock_character {
+            if input.unlock_character {
+                inertia.stop();
+            } else if let Some(center) = orbit {
+                gc.consume_orbit_input(&input, &center);
+            } else if let Some(target) = look_at {
+                gc.consume_look_at_input(&input, &target);
+            } else {
                 gc.consume_input(&input);
             };
+
+            if let Some(start) = &starting_point {
+                if leash.check(gc, &start.pos) && !sequence.points.is_empty() {
+                    warn!("Sequence cleaned to prevent game crashing");
+                    sequence.points.clear();
+                }
+            }
         }
 
         input.reset();
@@ -319,4 +778,5 @@ fn patch(_lib: LPVOID) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[cfg(windows)]
 memory_rs::main_dll!(wrapper);
@@ -0,0 +1,123 @@
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+/// How the view direction and the up-vector are interpolated between keyframes.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Orientation {
+    /// Spherical linear interpolation. Constant angular speed, but the rotation changes abruptly
+    /// on every keyframe.
+    Slerp,
+    /// Spherical quadrangle interpolation. Like `Slerp`, but smooth across keyframes.
+    #[default]
+    Squad,
+}
+
+/// Builds the rotation that takes +Z to `forward` and +Y to `up`.
+pub fn to_quat(forward: &glm::Vec3, up: &glm::Vec3) -> glm::Quat {
+    let forward: glm::Vec3 = glm::normalize(forward);
+    let mut right: glm::Vec3 = up.cross(&forward);
+    // If the up-vector is parallel to the view direction the roll is undefined, so we just pick one.
+    if glm::length(&right) < 1e-6 {
+        right = glm::vec3(0., 0., 1.).cross(&forward);
+        if glm::length(&right) < 1e-6 {
+            right = glm::vec3(1., 0., 0.);
+        }
+    }
+    let right = glm::normalize(&right);
+    let up = forward.cross(&right);
+
+    glm::mat3_to_quat(&glm::Mat3::from_columns(&[right, up, forward]))
+}
+
+/// Returns the view direction and the up-vector of a rotation built with `to_quat`.
+pub fn from_quat(q: &glm::Quat) -> (glm::Vec3, glm::Vec3) {
+    let q = glm::quat_normalize(q);
+    let forward = glm::quat_rotate_vec3(&q, &glm::vec3(0., 0., 1.));
+    let up = glm::quat_rotate_vec3(&q, &glm::vec3(0., 1., 0.));
+
+    (forward, up)
+}
+
+/// Flips `q` if needed so it lies in the same hemisphere as `reference`, otherwise the
+/// interpolation would take the long way around.
+fn align(q: &glm::Quat, reference: &glm::Quat) -> glm::Quat {
+    if glm::quat_dot(q, reference) < 0. {
+        -q
+    } else {
+        *q
+    }
+}
+
+fn slerp(a: &glm::Quat, b: &glm::Quat, t: f32) -> glm::Quat {
+    let b = align(b, a);
+    let cos = glm::quat_dot(a, &b).min(1.);
+
+    // Too close to each other, slerp would divide by zero.
+    if cos > 0.9995 {
+        return glm::quat_normalize(&glm::quat_lerp(a, &b, t));
+    }
+
+    let angle = cos.acos();
+    let sin = angle.sin();
+    a * (((1. - t) * angle).sin() / sin) + b * ((t * angle).sin() / sin)
+}
+
+/// Logarithm of a unit quaternion. `glm::quat_log` returns NaN for the identity, which is what we
+/// get between two keyframes with the same rotation.
+fn log(q: &glm::Quat) -> glm::Quat {
+    let v = glm::vec3(q[0], q[1], q[2]);
+    let length = glm::length(&v);
+    if length < 1e-6 {
+        return glm::quat(0., 0., 0., 0.);
+    }
+
+    let v = v * (length.atan2(q[3]) / length);
+    glm::quat(v[0], v[1], v[2], 0.)
+}
+
+/// Inverse of `log`.
+fn exp(q: &glm::Quat) -> glm::Quat {
+    let v = glm::vec3(q[0], q[1], q[2]);
+    let angle = glm::length(&v);
+    if angle < 1e-6 {
+        return glm::quat_identity();
+    }
+
+    let v = v * (angle.sin() / angle);
+    glm::quat(v[0], v[1], v[2], angle.cos())
+}
+
+/// Inner control point of `q` for squad.
+fn squad_control(previous: &glm::Quat, q: &glm::Quat, next: &glm::Quat) -> glm::Quat {
+    let inverse = glm::quat_inverse(q);
+    let to_next = log(&(inverse * next));
+    let to_previous = log(&(inverse * previous));
+
+    glm::quat_normalize(&(q * exp(&((to_next + to_previous) * -0.25))))
+}
+
+impl Orientation {
+    /// Interpolates the rotation between `q1` and `q2` at `t` (0 to 1), using `q0` and `q3` as
+    /// the neighbouring keyframes.
+    pub fn interpolate(
+        &self,
+        t: f32,
+        q0: &glm::Quat,
+        q1: &glm::Quat,
+        q2: &glm::Quat,
+        q3: &glm::Quat,
+    ) -> glm::Quat {
+        let q2 = align(q2, q1);
+        match self {
+            Orientation::Slerp => slerp(q1, &q2, t),
+            Orientation::Squad => {
+                let q0 = align(q0, q1);
+                let q3 = align(q3, &q2);
+                let s1 = squad_control(&q0, q1, &q2);
+                let s2 = squad_control(q1, &q2, &q3);
+
+                slerp(&slerp(q1, &q2, t), &slerp(&s1, &s2, t), 2. * t * (1. - t))
+            }
+        }
+    }
+}
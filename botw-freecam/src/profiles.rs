@@ -0,0 +1,741 @@
+use crate::scanner::Pattern;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+/// Entries in Cemu's jump table, one for every 4 bytes of PPC code.
+pub const JUMP_TABLE_LEN: usize = 0x8800000 / 0x8;
+
+/// Bytes from the start of a recompiled function where its signature is searched.
+const SEARCH_LEN: usize = 0x400;
+
+/// Largest `search_len` a profile can use. Reading too far past a function can leave Cemu's
+/// code cache and crash the game.
+const MAX_SEARCH_LEN: usize = 0x1000;
+
+/// A function recompiled by Cemu, found through the jump table by its PPC address.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Function {
+    #[serde(deserialize_with = "number")]
+    pub address: u32,
+    /// Offset of the instruction we care about from the start of the recompiled function. When
+    /// there's a signature, it's only used to choose between several matches.
+    #[serde(deserialize_with = "number")]
+    pub offset: usize,
+    /// Pattern of the instruction, searched in the first `search_len` bytes of the function so
+    /// it's still found when the recompiler moves it a bit.
+    #[serde(default)]
+    pub signature: Pattern,
+    #[serde(default = "default_search_len", deserialize_with = "number")]
+    pub search_len: usize,
+}
+
+fn default_search_len() -> usize {
+    SEARCH_LEN
+}
+
+impl Function {
+    fn index(&self) -> usize {
+        self.address as usize / 4
+    }
+
+    /// Finds the instruction inside the function that starts at `start`.
+    fn locate(&self, start: usize) -> Result<usize, String> {
+        find(&self.signature, start, self.offset, self.search_len)
+    }
+}
+
+/// Finds `signature` in the `search_len` bytes after `start`, using `offset` to choose between
+/// several matches. Without a signature the instruction is just at `offset`.
+fn find(
+    signature: &Pattern,
+    start: usize,
+    offset: usize,
+    search_len: usize,
+) -> Result<usize, String> {
+    if signature.is_empty() {
+        return Ok(start + offset);
+    }
+
+    let body = unsafe { std::slice::from_raw_parts(start as *const u8, search_len) };
+    let matches = signature.find_all(body);
+
+    match matches[..] {
+        [] => Err(format!(
+            "`{}` wasn't found, the bytes at {:x} are {:x?}",
+            signature,
+            offset,
+            &body[offset..offset + signature.len()]
+        )),
+        [found] => Ok(start + found),
+        _ if matches.contains(&offset) => Ok(start + offset),
+        _ => Err(format!(
+            "`{}` is ambiguous, it was found at {:x?}",
+            signature, matches
+        )),
+    }
+}
+
+/// Code that replaces the camera instruction, which depends on how Cemu recompiled it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interceptor {
+    /// `movbe [r13 + rdx + 654h], r14d`, used on CPUs with MOVBE.
+    #[default]
+    Movbe,
+    /// `bswap r14d` and `mov [r13 + rdx + 654h], r14d`, used on older CPUs or when a cheat
+    /// disables MOVBE.
+    Bswap,
+}
+
+impl Interceptor {
+    /// Interceptor for the code Cemu emits on this CPU.
+    pub fn for_cpu() -> Self {
+        if cpu_has_movbe() {
+            Interceptor::Movbe
+        } else {
+            Interceptor::Bswap
+        }
+    }
+
+    /// Bytes replaced by the detour: the camera instruction and the `cvtss2sd` after it.
+    pub fn detour_len(&self) -> usize {
+        match self {
+            Interceptor::Movbe => 14,
+            Interceptor::Bswap => 15,
+        }
+    }
+}
+
+/// Whether the CPU supports MOVBE, which Cemu uses to swap the bytes of the game's memory.
+// `__cpuid` is only unsafe in older versions of Rust.
+#[cfg(target_arch = "x86_64")]
+#[allow(unused_unsafe)]
+pub fn cpu_has_movbe() -> bool {
+    let info = unsafe { std::arch::x86_64::__cpuid(1) };
+    info.ecx & (1 << 22) != 0
+}
+
+/// Cemu only runs on x86_64, this is just so the crate builds elsewhere.
+#[cfg(not(target_arch = "x86_64"))]
+pub fn cpu_has_movbe() -> bool {
+    true
+}
+
+/// Function a NOP is relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Base {
+    Camera,
+    Rotation,
+}
+
+/// Instructions that write to the camera and have to be disabled while the freecam is active.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Nop {
+    pub base: Base,
+    /// Offset from the base instruction. When there's a signature, it's searched in the
+    /// `SEARCH_LEN` bytes after the base instead and the offset only chooses between matches.
+    #[serde(deserialize_with = "number")]
+    pub offset: usize,
+    #[serde(default)]
+    pub signature: Pattern,
+    /// Bytes to disable, which default to the length of the signature.
+    #[serde(default)]
+    pub length: usize,
+}
+
+impl Nop {
+    fn length(&self) -> usize {
+        if self.length == 0 {
+            self.signature.len()
+        } else {
+            self.length
+        }
+    }
+}
+
+/// Where the camera code is in a version of the game running on a version of Cemu.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub title_ids: Vec<String>,
+    #[serde(default)]
+    pub game_version: String,
+    #[serde(default)]
+    pub cemu_version: String,
+    #[serde(default)]
+    pub interceptor: Interceptor,
+    /// The camera function needs a signature, so we know whether the profile matches the game.
+    pub camera: Function,
+    pub rotation: Function,
+    pub nops: Vec<Nop>,
+}
+
+/// Addresses of a profile once the game recompiled its functions.
+#[derive(Debug)]
+pub struct CameraOffsets {
+    pub camera: usize,
+    pub interceptor: Interceptor,
+    /// Address and length of every NOP.
+    pub nops: Vec<(usize, usize)>,
+}
+
+impl Profile {
+    /// Checks that the profile can be used, so a mistake in it fails with an error instead of
+    /// patching random memory.
+    pub fn validate(&self) -> Result<(), String> {
+        let error = |e: String| format!("Profile `{}`: {}", self.name, e);
+
+        for (name, function) in [("camera", &self.camera), ("rotation", &self.rotation)].iter() {
+            if function.search_len > MAX_SEARCH_LEN {
+                return Err(error(format!(
+                    "the {} search_len is bigger than {:#x}",
+                    name, MAX_SEARCH_LEN
+                )));
+            }
+            if function.offset + function.signature.len() > function.search_len {
+                return Err(error(format!(
+                    "the {} instruction is outside of the searched bytes",
+                    name
+                )));
+            }
+            if !function.signature.is_empty() && function.signature.is_wildcard() {
+                return Err(error(format!("the {} signature only has wildcards", name)));
+            }
+            if function.address % 4 != 0 {
+                return Err(error(format!(
+                    "the {} function isn't aligned to 4 bytes",
+                    name
+                )));
+            }
+            if function.index() >= JUMP_TABLE_LEN {
+                return Err(error(format!(
+                    "the {} function is outside of the jump table",
+                    name
+                )));
+            }
+        }
+
+        if self.camera.signature.is_empty() {
+            return Err(error("the camera function needs a signature".to_string()));
+        }
+
+        // The detour replaces these bytes and runs them again, so they have to be the ones the
+        // interceptor expects.
+        let detour_len = self.interceptor.detour_len();
+        let signature = &self.camera.signature.0;
+        if signature.len() < detour_len || signature[..detour_len].contains(&None) {
+            return Err(error(format!(
+                "the camera signature has to match the {} bytes replaced by the detour without \
+                 wildcards",
+                detour_len
+            )));
+        }
+
+        if self.nops.is_empty() {
+            return Err(error("there are no NOPs".to_string()));
+        }
+
+        for nop in &self.nops {
+            if nop.length() == 0 {
+                return Err(error(format!("the NOP at {:x} is empty", nop.offset)));
+            }
+            if nop.offset + nop.signature.len() > SEARCH_LEN {
+                return Err(error(format!(
+                    "the NOP at {:x} is outside of the searched bytes",
+                    nop.offset
+                )));
+            }
+            if !nop.signature.is_empty() && nop.signature.is_wildcard() {
+                return Err(error(format!(
+                    "the signature of the NOP at {:x} only has wildcards",
+                    nop.offset
+                )));
+            }
+
+            // The camera function starts with our detour, which can't be overwritten.
+            if nop.base == Base::Camera && nop.offset < self.interceptor.detour_len() {
+                return Err(error(format!(
+                    "the NOP at {:x} overlaps the detour",
+                    nop.offset
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the camera function was already recompiled, i.e. the game is in the world.
+    pub fn is_compiled(&self, table: &[usize]) -> bool {
+        table[self.camera.index()] != table[0]
+    }
+
+    /// Finds the instructions of the profile once the camera function was recompiled.
+    pub fn resolve(&self, table: &[usize]) -> Result<CameraOffsets, String> {
+        self.resolve_at(table[self.camera.index()], table[self.rotation.index()])
+    }
+
+    /// Same as `resolve`, with the recompiled functions starting at `camera` and `rotation`.
+    fn resolve_at(&self, camera: usize, rotation: usize) -> Result<CameraOffsets, String> {
+        let camera = self
+            .camera
+            .locate(camera)
+            .map_err(|e| format!("Camera function: {}", e))?;
+        let rotation = self
+            .rotation
+            .locate(rotation)
+            .map_err(|e| format!("Rotation function: {}", e))?;
+        let nops = self
+            .nops
+            .iter()
+            .map(|nop| {
+                let base = match nop.base {
+                    Base::Camera => camera,
+                    Base::Rotation => rotation,
+                };
+                let address = find(&nop.signature, base, nop.offset, SEARCH_LEN)
+                    .map_err(|e| format!("NOP at {:x}: {}", nop.offset, e))?;
+
+                if address >= camera && address < camera + self.interceptor.detour_len() {
+                    return Err(format!(
+                        "NOP at {:x} was found inside the detour",
+                        nop.offset
+                    ));
+                }
+
+                Ok((address, nop.length()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(CameraOffsets {
+            camera,
+            interceptor: self.interceptor,
+            nops,
+        })
+    }
+}
+
+/// Numbers can be written as integers or as hex strings (`"0x2C085FC"`), since JSON doesn't have
+/// hex literals.
+fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: TryFrom<u64>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Integer(u64),
+        Text(String),
+    }
+
+    let value = match Number::deserialize(deserializer)? {
+        Number::Integer(value) => value,
+        Number::Text(text) => {
+            let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+            u64::from_str_radix(digits, 16)
+                .map_err(|_| serde::de::Error::custom(format!("`{}` is not a hex number", text)))?
+        }
+    };
+
+    T::try_from(value).map_err(|_| serde::de::Error::custom(format!("{:#x} is too big", value)))
+}
+
+/// Profile files (`.toml` or `.json`) inside `dir`, sorted by name.
+pub fn profile_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                let extension = path.extension().and_then(|e| e.to_str());
+                matches!(extension, Some("toml") | Some("json"))
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    paths.sort();
+    paths
+}
+
+/// Loads and validates a profile written by hand.
+pub fn load_profile(path: &Path) -> Result<Profile, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+
+    let profile: Profile = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+        _ => toml::from_str(&contents).map_err(|e| e.to_string()),
+    }
+    .map_err(|e| format!("{} is not a valid profile: {}", path.display(), e))?;
+
+    profile
+        .validate()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(profile)
+}
+
+/// Moves the profiles made for `interceptor` first, keeping their order otherwise. The others are
+/// still tried, since a cheat can make Cemu emit the other kind of code.
+pub fn prefer_interceptor(profiles: &mut [Profile], interceptor: Interceptor) {
+    profiles.sort_by_key(|profile| profile.interceptor != interceptor);
+}
+
+/// Profiles that come with the freecam.
+pub fn builtin_profiles() -> Vec<Profile> {
+    let nop = |base, offset, signature: &str| Nop {
+        base,
+        offset,
+        signature: Pattern::parse(signature).unwrap(),
+        length: 0,
+    };
+
+    let movbe = Profile {
+        name: "Breath of the Wild v1.5.0 (DLC Ver.3.0) on Cemu 1.26.2".to_string(),
+        title_ids: vec![
+            "00050000101C9300".to_string(),
+            "00050000101C9400".to_string(),
+            "00050000101C9500".to_string(),
+        ],
+        game_version: "1.5.0".to_string(),
+        cemu_version: "1.26.2".to_string(),
+        interceptor: Interceptor::Movbe,
+        camera: Function {
+            address: 0x2C085FC,
+            offset: 0x7E,
+            // movbe [r13 + rdx + 654h], r14d; cvtss2sd xmm0, xmm0, which the detour replaces
+            signature: Pattern::parse("45 0F 38 F1 B4 15 54 06 00 00 F3 0F 5A C0").unwrap(),
+            search_len: SEARCH_LEN,
+        },
+        rotation: Function {
+            address: 0x2e57fdc,
+            offset: 0x57,
+            // The three `movbe [r13 + reg + disp8], reg` that write the rotation, 0x14 bytes
+            // apart.
+            signature: Pattern::parse(&rotation_writers("?? 0F 38 F1 ?? ?? ??", 0x14)).unwrap(),
+            search_len: SEARCH_LEN,
+        },
+        nops: vec![
+            // movbe [r13 + reg + 630h + field], reg for the pos, focus and up-vector of the
+            // camera, which is right before the fov.
+            nop(Base::Camera, 0x17, "?? 0F 38 F1 ?? ?? 30 06 00 00"),
+            nop(Base::Camera, 0x55, "?? 0F 38 F1 ?? ?? 34 06 00 00"),
+            nop(Base::Camera, 0xC2, "?? 0F 38 F1 ?? ?? 38 06 00 00"),
+            nop(Base::Camera, 0xD9, "?? 0F 38 F1 ?? ?? 3C 06 00 00"),
+            nop(Base::Camera, 0x117, "?? 0F 38 F1 ?? ?? 40 06 00 00"),
+            nop(Base::Camera, 0x12E, "?? 0F 38 F1 ?? ?? 44 06 00 00"),
+            nop(Base::Camera, 0x15D, "?? 0F 38 F1 ?? ?? 48 06 00 00"),
+            nop(Base::Camera, 0x174, "?? 0F 38 F1 ?? ?? 4C 06 00 00"),
+            nop(Base::Camera, 0x22A, "?? 0F 38 F1 ?? ?? 50 06 00 00"),
+            // Rotation, found together by the rotation signature.
+            nop(Base::Rotation, 0x0, "?? 0F 38 F1 ?? ?? ??"),
+            nop(Base::Rotation, 0x14, "?? 0F 38 F1 ?? ?? ??"),
+            nop(Base::Rotation, 0x28, "?? 0F 38 F1 ?? ?? ??"),
+        ],
+    };
+
+    // Without MOVBE, Cemu swaps the register first and writes it with a plain `mov`. Only the
+    // `mov` is disabled, so the register is left swapped like the game code expects. The offsets
+    // only choose between matches, so they're the ones of the movbe code after the `bswap`.
+    let bswap = Profile {
+        name: format!("{} (without movbe)", movbe.name),
+        interceptor: Interceptor::Bswap,
+        camera: Function {
+            // bswap r14d; mov [r13 + rdx + 654h], r14d; cvtss2sd xmm0, xmm0
+            signature: Pattern::parse("41 0F CE 45 89 B4 15 54 06 00 00 F3 0F 5A C0").unwrap(),
+            ..movbe.camera.clone()
+        },
+        rotation: Function {
+            // bswap e??; mov [r13 + reg + disp8], e??
+            signature: Pattern::parse(&rotation_writers("0F ?? ?? 89 ?? ?? ??", 0x14)).unwrap(),
+            ..movbe.rotation.clone()
+        },
+        nops: vec![
+            // mov [r13 + reg + 630h + field], reg
+            nop(Base::Camera, 0x1A, "?? 89 ?? ?? 30 06 00 00"),
+            nop(Base::Camera, 0x58, "?? 89 ?? ?? 34 06 00 00"),
+            nop(Base::Camera, 0xC5, "?? 89 ?? ?? 38 06 00 00"),
+            nop(Base::Camera, 0xDC, "?? 89 ?? ?? 3C 06 00 00"),
+            nop(Base::Camera, 0x11A, "?? 89 ?? ?? 40 06 00 00"),
+            nop(Base::Camera, 0x131, "?? 89 ?? ?? 44 06 00 00"),
+            nop(Base::Camera, 0x160, "?? 89 ?? ?? 48 06 00 00"),
+            nop(Base::Camera, 0x177, "?? 89 ?? ?? 4C 06 00 00"),
+            nop(Base::Camera, 0x22D, "?? 89 ?? ?? 50 06 00 00"),
+            nop(Base::Rotation, 0x2, "?? 89 ?? ?? ??"),
+            nop(Base::Rotation, 0x16, "?? 89 ?? ?? ??"),
+            nop(Base::Rotation, 0x2A, "?? 89 ?? ?? ??"),
+        ],
+        ..movbe.clone()
+    };
+
+    vec![movbe, bswap]
+}
+
+/// Signature of three `writer`s that start `spacing` bytes apart.
+fn rotation_writers(writer: &str, spacing: usize) -> String {
+    let gap = spacing - Pattern::parse(writer).unwrap().len();
+    let gap = vec!["??"; gap].join(" ");
+    format!("{0} {1} {0} {1} {0}", writer, gap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML_PROFILE: &str = r#"
+name = "Test"
+title_ids = ["00050000101C9400"]
+
+[camera]
+address = 0x2C085FC
+offset = 0x7E
+signature = "45 0F 38 F1 B4 15 54 06 00 00 F3 0F 5A C0"
+
+[rotation]
+address = 0x2E57FDC
+offset = 0x57
+
+[[nops]]
+base = "camera"
+offset = 0x17
+length = 10
+
+[[nops]]
+base = "rotation"
+offset = 0x14
+length = 7
+"#;
+
+    const JSON_PROFILE: &str = r#"{
+        "name": "Test",
+        "camera": {
+            "address": "0x2C085FC",
+            "offset": 126,
+            "signature": [69, 15, 56, 241, 180, 21, 84, 6, 0, 0, 243, 15, 90, 192]
+        },
+        "rotation": { "address": 48594908, "offset": "0x57" },
+        "nops": [{ "base": "camera", "offset": "0x17", "length": 10 }]
+    }"#;
+
+    #[test]
+    fn builtin_profiles_are_valid() {
+        for profile in builtin_profiles() {
+            assert_eq!(profile.validate(), Ok(()), "{}", profile.name);
+        }
+    }
+
+    #[test]
+    fn profiles_load_from_toml_and_json() {
+        let toml: Profile = toml::from_str(TOML_PROFILE).unwrap();
+        let json: Profile = serde_json::from_str(JSON_PROFILE).unwrap();
+
+        for profile in &[&toml, &json] {
+            assert_eq!(profile.validate(), Ok(()));
+            assert_eq!(profile.camera.address, 0x2C085FC);
+            assert_eq!(profile.camera.offset, 0x7E);
+            assert_eq!(profile.camera.search_len, SEARCH_LEN);
+            assert_eq!(profile.rotation.address, 0x2E57FDC);
+            assert_eq!(profile.rotation.offset, 0x57);
+            assert_eq!(profile.nops[0].offset, 0x17);
+        }
+        assert_eq!(toml.camera.signature, json.camera.signature);
+        assert_eq!(toml.nops.len(), 2);
+    }
+
+    #[test]
+    fn numbers_can_be_hex_strings() {
+        let function = |address: &str| {
+            serde_json::from_str::<Function>(&format!(r#"{{"address": {}, "offset": 0}}"#, address))
+                .map(|f| f.address)
+        };
+
+        assert_eq!(function("16").unwrap(), 16);
+        assert_eq!(function(r#""0x10""#).unwrap(), 16);
+        assert_eq!(function(r#""0X2c085fc""#).unwrap(), 0x2C085FC);
+        assert_eq!(function(r#""10""#).unwrap(), 16);
+        assert!(function(r#""0xZZ""#).is_err());
+        assert!(function(r#""0x100000000""#).is_err());
+    }
+
+    #[test]
+    fn mistakes_in_profiles_are_rejected() {
+        let mut profile: Profile = toml::from_str(TOML_PROFILE).unwrap();
+        profile.camera.address += 2;
+        assert!(profile.validate().is_err());
+
+        let mut profile: Profile = toml::from_str(TOML_PROFILE).unwrap();
+        profile.rotation.search_len = 0x100000;
+        assert!(profile.validate().is_err());
+
+        let mut profile: Profile = toml::from_str(TOML_PROFILE).unwrap();
+        profile.nops[0].offset = 0x4;
+        assert!(profile.validate().is_err());
+
+        let mut profile: Profile = toml::from_str(TOML_PROFILE).unwrap();
+        profile.camera.signature = Pattern::default();
+        assert!(profile.validate().is_err());
+
+        let mut profile: Profile = toml::from_str(TOML_PROFILE).unwrap();
+        profile.camera.signature.0[5] = None;
+        assert!(profile.validate().is_err());
+
+        let mut profile: Profile = toml::from_str(TOML_PROFILE).unwrap();
+        profile.camera.signature.0.truncate(10);
+        assert!(profile.validate().is_err());
+
+        assert!(toml::from_str::<Profile>(&format!("{}\nunknown = 1", TOML_PROFILE)).is_err());
+    }
+
+    #[test]
+    fn profiles_for_the_cpu_are_tried_first() {
+        let profile = |name: &str, interceptor| Profile {
+            name: name.to_string(),
+            interceptor,
+            ..toml::from_str(TOML_PROFILE).unwrap()
+        };
+        let mut profiles = vec![
+            profile("user movbe", Interceptor::Movbe),
+            profile("user bswap", Interceptor::Bswap),
+            profile("builtin movbe", Interceptor::Movbe),
+            profile("builtin bswap", Interceptor::Bswap),
+        ];
+
+        prefer_interceptor(&mut profiles, Interceptor::Bswap);
+
+        let names: Vec<_> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["user bswap", "builtin bswap", "user movbe", "builtin movbe"]
+        );
+    }
+
+    /// Bytes that match `pattern`, with a made-up byte in place of the wildcards.
+    fn assemble(pattern: &Pattern) -> Vec<u8> {
+        pattern.0.iter().map(|byte| byte.unwrap_or(0x44)).collect()
+    }
+
+    /// Recompiled camera and rotation functions with the instructions of `profile`. The camera
+    /// NOPs are `shift` bytes after where the profile expects them, and so is the rotation code.
+    fn recompiled(profile: &Profile, shift: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut camera = vec![0x90; 0x800];
+        let mut rotation = vec![0x90; 0x800];
+        let write = |code: &mut Vec<u8>, at: usize, pattern: &Pattern| {
+            code[at..at + pattern.len()].copy_from_slice(&assemble(pattern));
+        };
+
+        let camera_at = profile.camera.offset;
+        let rotation_at = profile.rotation.offset + shift;
+        write(&mut camera, camera_at, &profile.camera.signature);
+        write(&mut rotation, rotation_at, &profile.rotation.signature);
+        for nop in &profile.nops {
+            match nop.base {
+                Base::Camera => write(&mut camera, camera_at + nop.offset + shift, &nop.signature),
+                Base::Rotation => write(&mut rotation, rotation_at + nop.offset, &nop.signature),
+            }
+        }
+
+        (camera, rotation)
+    }
+
+    /// Checks that every instruction of `profile` is found in code shifted by `shift` bytes.
+    fn assert_resolves(profile: &Profile, shift: usize) {
+        let (camera, rotation) = recompiled(profile, shift);
+        let (camera, rotation) = (camera.as_ptr() as usize, rotation.as_ptr() as usize);
+
+        let offsets = profile.resolve_at(camera, rotation).unwrap();
+
+        let camera = camera + profile.camera.offset;
+        let rotation = rotation + profile.rotation.offset + shift;
+        assert_eq!(offsets.camera, camera);
+        for (nop, &(address, length)) in profile.nops.iter().zip(&offsets.nops) {
+            let expected = match nop.base {
+                Base::Camera => camera + nop.offset + shift,
+                Base::Rotation => rotation + nop.offset,
+            };
+            assert_eq!(address, expected, "NOP at {:x}", nop.offset);
+            assert_eq!(length, nop.signature.len());
+        }
+    }
+
+    #[test]
+    fn builtin_profiles_find_their_instructions() {
+        for profile in builtin_profiles() {
+            assert_resolves(&profile, 0);
+        }
+    }
+
+    #[test]
+    fn builtin_profiles_find_moved_instructions() {
+        for profile in builtin_profiles() {
+            for &shift in &[1, 3, 8] {
+                assert_resolves(&profile, shift);
+            }
+        }
+    }
+
+    #[test]
+    fn builtin_bswap_profile_finds_cemus_code() {
+        let profile = builtin_profiles()
+            .into_iter()
+            .find(|profile| profile.interceptor == Interceptor::Bswap)
+            .unwrap();
+        let mut camera = vec![0x90; 0x800];
+        let mut rotation = vec![0x90; 0x800];
+
+        // bswap r14d; mov [r13 + rdx + 654h], r14d; cvtss2sd xmm0, xmm0
+        let hook = [
+            0x41, 0x0F, 0xCE, 0x45, 0x89, 0xB4, 0x15, 0x54, 0x06, 0x00, 0x00, 0xF3, 0x0F, 0x5A,
+            0xC0,
+        ];
+        camera[0x80..0x80 + hook.len()].copy_from_slice(&hook);
+        // bswap r12d; mov [r13 + rdx + 630h + field], r12d, a bit further than expected
+        let mut writers = vec![];
+        for (i, nop) in profile.nops.iter().take(9).enumerate() {
+            let at = 0x80 + nop.offset + 5;
+            let field = 0x30 + i as u8 * 4;
+            let writer = [
+                0x41, 0x0F, 0xCC, 0x45, 0x89, 0xA4, 0x15, field, 0x06, 0x00, 0x00,
+            ];
+            camera[at - 3..at + 8].copy_from_slice(&writer);
+            writers.push(at);
+        }
+        // bswap eax; mov [r13 + rsi + 10h + 4 * i], eax, 0x14 bytes apart
+        for i in 0..3 {
+            let at = 0x60 + i * 0x14;
+            let writer = [0x0F, 0xC8, 0x41, 0x89, 0x44, 0x35, 0x10 + i as u8 * 4];
+            rotation[at..at + 7].copy_from_slice(&writer);
+            writers.push(at + 2);
+        }
+
+        let (camera, rotation) = (camera.as_ptr() as usize, rotation.as_ptr() as usize);
+        let offsets = profile.resolve_at(camera, rotation).unwrap();
+
+        assert_eq!(offsets.camera, camera + 0x80);
+        assert_eq!(offsets.interceptor, Interceptor::Bswap);
+        for (i, (&(address, length), at)) in offsets.nops.iter().zip(writers).enumerate() {
+            let start = if i < 9 { camera } else { rotation };
+            assert_eq!(address, start + at, "NOP {}", i);
+            assert_eq!(length, if i < 9 { 8 } else { 5 });
+        }
+    }
+
+    #[test]
+    fn repeated_instructions_are_reported_as_ambiguous() {
+        let profile = &builtin_profiles()[0];
+        let (mut camera, rotation) = recompiled(profile, 2);
+        let writer = assemble(&profile.nops[0].signature);
+        camera[0x300..0x300 + writer.len()].copy_from_slice(&writer);
+
+        let error = profile
+            .resolve_at(camera.as_ptr() as usize, rotation.as_ptr() as usize)
+            .unwrap_err();
+
+        assert!(error.contains("ambiguous"), "{}", error);
+    }
+}
@@ -0,0 +1,88 @@
+use serde::Deserialize;
+use std::fmt;
+
+/// Bytes to search for, where `None` matches any byte. Written like `"45 0F 38 ?? B4"`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pattern(pub Vec<Option<u8>>);
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        text.split_whitespace()
+            .map(|byte| match byte {
+                "?" | "??" => Ok(None),
+                _ => u8::from_str_radix(byte, 16)
+                    .map(Some)
+                    .map_err(|_| format!("`{}` is not a hex byte or a wildcard", byte)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Pattern)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether every byte is a wildcard, which would match anything.
+    pub fn is_wildcard(&self) -> bool {
+        self.0.iter().all(Option::is_none)
+    }
+
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() == self.len()
+            && self
+                .0
+                .iter()
+                .zip(bytes)
+                .all(|(p, b)| p.is_none() || *p == Some(*b))
+    }
+
+    /// Offsets of every match inside `haystack`.
+    pub fn find_all(&self, haystack: &[u8]) -> Vec<usize> {
+        if self.is_empty() || haystack.len() < self.len() {
+            return vec![];
+        }
+
+        haystack
+            .windows(self.len())
+            .enumerate()
+            .filter(|(_, window)| self.matches(window))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self
+            .0
+            .iter()
+            .map(|b| match b {
+                Some(b) => format!("{:02X}", b),
+                None => "??".to_string(),
+            })
+            .collect();
+
+        write!(f, "{}", bytes.join(" "))
+    }
+}
+
+/// Patterns can be a list of bytes or a string with wildcards.
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Bytes {
+            List(Vec<u8>),
+            Text(String),
+        }
+
+        match Bytes::deserialize(deserializer)? {
+            Bytes::List(bytes) => Ok(Pattern(bytes.into_iter().map(Some).collect())),
+            Bytes::Text(text) => Pattern::parse(&text).map_err(serde::de::Error::custom),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::dolly::Sequence;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Version of the sequence file format. Bump it every time a change breaks
+/// the compatibility with older files.
+pub const SEQUENCE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SequenceFile {
+    version: u32,
+    #[serde(flatten)]
+    sequence: Sequence,
+}
+
+/// (De)serializes a `glm::Vec3` as a plain `[x, y, z]` array so the files
+/// stay readable and we don't depend on nalgebra's serde feature.
+pub mod vec3 {
+    use nalgebra_glm as glm;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &glm::Vec3, serializer: S) -> Result<S::Ok, S::Error> {
+        [v[0], v[1], v[2]].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<glm::Vec3, D::Error> {
+        let [x, y, z] = <[f32; 3]>::deserialize(deserializer)?;
+        Ok(glm::vec3(x, y, z))
+    }
+}
+
+/// Same as `vec3`, for optional points that are left out of the file when they're not set.
+pub mod optional_vec3 {
+    use nalgebra_glm as glm;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        v: &Option<glm::Vec3>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        v.map(|v| [v[0], v[1], v[2]]).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<glm::Vec3>, D::Error> {
+        let v = Option::<[f32; 3]>::deserialize(deserializer)?;
+        Ok(v.map(|[x, y, z]| glm::vec3(x, y, z)))
+    }
+}
+
+/// Path of the file that stores the sequence of a given slot.
+pub fn slot_path(dir: &Path, slot: u32) -> PathBuf {
+    dir.join(format!("slot_{}.json", slot))
+}
+
+pub fn save_sequence(path: &Path, sequence: &Sequence) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let file = SequenceFile {
+        version: SEQUENCE_VERSION,
+        sequence: sequence.clone(),
+    };
+
+    let contents = serde_json::to_string_pretty(&file)?;
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
+
+pub fn load_sequence(path: &Path) -> Result<Sequence, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let file: SequenceFile = serde_json::from_str(&contents)
+        .map_err(|e| format!("{} is not a valid sequence: {}", path.display(), e))?;
+
+    if file.version > SEQUENCE_VERSION {
+        return Err(format!(
+            "{} was saved with a newer version of the freecam (v{} > v{})",
+            path.display(),
+            file.version,
+            SEQUENCE_VERSION
+        )
+        .into());
+    }
+
+    let mut sequence = file.sequence;
+    for (i, point) in sequence.points.iter_mut().enumerate() {
+        if !point.is_valid() {
+            return Err(format!("Point {} of {} has invalid values", i + 1, path.display()).into());
+        }
+        point.limit_timing();
+    }
+    if let Some(look_at) = sequence.look_at {
+        if !look_at.iter().all(|v| v.is_finite()) {
+            return Err(format!("The look-at point of {} is invalid", path.display()).into());
+        }
+    }
+
+    Ok(sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm as glm;
+
+    /// Loads a sequence file with `points`, written in its JSON form.
+    fn load(name: &str, points: &str) -> Result<Sequence, Box<dyn std::error::Error>> {
+        let path = std::env::temp_dir().join(format!("botw-freecam-{}.json", name));
+        let contents = format!(r#"{{"version": 1, "points": [{}]}}"#, points);
+        std::fs::write(&path, contents).unwrap();
+        let sequence = load_sequence(&path);
+        std::fs::remove_file(&path).unwrap();
+        sequence
+    }
+
+    fn point(pos: &str, fov: f32, timing: &str) -> String {
+        format!(
+            r#"{{"pos": {}, "focus": [0, 0, 1], "rot": [0, 1, 0], "fov": {}{}}}"#,
+            pos, fov, timing
+        )
+    }
+
+    #[test]
+    fn saved_sequences_load_back() {
+        let points = format!(
+            "{}, {}",
+            point("[0, 0, 0]", 0.9, ""),
+            point("[1, 2, 3]", 1.2, r#", "duration": 2.5, "hold": 1"#)
+        );
+        let sequence = load("roundtrip", &points).unwrap();
+
+        let path = std::env::temp_dir().join("botw-freecam-roundtrip-saved.json");
+        save_sequence(&path, &sequence).unwrap();
+        let loaded = load_sequence(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.points.len(), 2);
+        assert_eq!(loaded.points[1].pos, glm::vec3(1., 2., 3.));
+        assert_eq!(loaded.points[1].duration, Some(2.5));
+        assert_eq!(loaded.points[1].hold, 1.);
+    }
+
+    #[test]
+    fn timing_edited_by_hand_is_limited() {
+        let points = point("[0, 0, 0]", 0.9, r#", "duration": 0.05, "hold": -3"#);
+        let sequence = load("timing", &points).unwrap();
+
+        assert_eq!(sequence.points[0].duration, None);
+        assert_eq!(sequence.points[0].hold, 0.);
+    }
+
+    #[test]
+    fn invalid_points_are_rejected() {
+        // JSON doesn't have NaN or infinity, but a number too big for an f32 is read as one.
+        assert!(load("infinite", &point("[1e39, 0, 0]", 0.9, "")).is_err());
+        assert!(load("no-fov", &point("[0, 0, 0]", 0., "")).is_err());
+        assert!(load("wide-fov", &point("[0, 0, 0]", 4., "")).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+/// Kind of spline used to go through the keyframes of a sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Spline {
+    /// Catmull-Rom with uniform parameterization. It overshoots and can form loops when the
+    /// keyframes are unevenly spaced.
+    #[default]
+    Uniform,
+    /// Catmull-Rom parameterized by the square root of the distance between keyframes. It never
+    /// forms cusps or self-intersections inside a segment.
+    Centripetal,
+    /// Catmull-Rom parameterized by the distance between keyframes.
+    Chordal,
+    /// Hermite spline with tangents controlled by tension, bias and continuity, all in [-1, 1].
+    KochanekBartels {
+        tension: f32,
+        bias: f32,
+        continuity: f32,
+    },
+}
+
+impl Spline {
+    /// Evaluates the segment between `p1` and `p2` at `t` (0 to 1), using `p0` and `p3` as the
+    /// neighbouring keyframes.
+    pub fn solve_eq(
+        &self,
+        t: f32,
+        p0: glm::Vec3,
+        p1: glm::Vec3,
+        p2: glm::Vec3,
+        p3: glm::Vec3,
+    ) -> glm::Vec3 {
+        match *self {
+            Spline::Uniform => {
+                let b0 = 0.5 * (-t.powi(3) + 2. * t.powi(2) - t);
+                let b1 = 0.5 * (3. * t.powi(3) - 5. * t.powi(2) + 2.);
+                let b2 = 0.5 * (-3. * t.powi(3) + 4. * t.powi(2) + t);
+                let b3 = 0.5 * (t.powi(3) - t.powi(2));
+
+                p0 * b0 + p1 * b1 + p2 * b2 + p3 * b3
+            }
+            Spline::Centripetal => catmull_rom(t, 0.5, p0, p1, p2, p3),
+            Spline::Chordal => catmull_rom(t, 1., p0, p1, p2, p3),
+            Spline::KochanekBartels {
+                tension,
+                bias,
+                continuity,
+            } => {
+                let (t_, b, c) = (tension, bias, continuity);
+
+                // Outgoing tangent of p1 and incoming tangent of p2.
+                let m1 = (p1 - p0) * ((1. - t_) * (1. + b) * (1. - c) / 2.)
+                    + (p2 - p1) * ((1. - t_) * (1. - b) * (1. + c) / 2.);
+                let m2 = (p2 - p1) * ((1. - t_) * (1. + b) * (1. + c) / 2.)
+                    + (p3 - p2) * ((1. - t_) * (1. - b) * (1. - c) / 2.);
+
+                hermite(t, p1, p2, m1, m2)
+            }
+        }
+    }
+
+    /// Next kind of spline, used to cycle through them with a single key.
+    pub fn next(&self) -> Self {
+        match self {
+            Spline::Uniform => Spline::Centripetal,
+            Spline::Centripetal => Spline::Chordal,
+            Spline::Chordal => Spline::KochanekBartels {
+                tension: 0.,
+                bias: 0.,
+                continuity: 0.,
+            },
+            Spline::KochanekBartels { .. } => Spline::Uniform,
+        }
+    }
+}
+
+fn hermite(t: f32, p1: glm::Vec3, p2: glm::Vec3, m1: glm::Vec3, m2: glm::Vec3) -> glm::Vec3 {
+    let h00 = 2. * t.powi(3) - 3. * t.powi(2) + 1.;
+    let h10 = t.powi(3) - 2. * t.powi(2) + t;
+    let h01 = -2. * t.powi(3) + 3. * t.powi(2);
+    let h11 = t.powi(3) - t.powi(2);
+
+    p1 * h00 + m1 * h10 + p2 * h01 + m2 * h11
+}
+
+/// Non-uniform Catmull-Rom, where the knots are spaced by the distance between the keyframes to
+/// the power of `alpha`. It's written in its Hermite form so repeated keyframes (like the ones at
+/// the ends of a sequence) don't divide by zero.
+fn catmull_rom(
+    t: f32,
+    alpha: f32,
+    p0: glm::Vec3,
+    p1: glm::Vec3,
+    p2: glm::Vec3,
+    p3: glm::Vec3,
+) -> glm::Vec3 {
+    let knot = |a: &glm::Vec3, b: &glm::Vec3| glm::distance(a, b).powf(alpha).max(1e-4);
+    let d0 = knot(&p0, &p1);
+    let d1 = knot(&p1, &p2);
+    let d2 = knot(&p2, &p3);
+
+    let m1 = ((p1 - p0) / d0 - (p2 - p0) / (d0 + d1) + (p2 - p1) / d1) * d1;
+    let m2 = ((p2 - p1) / d1 - (p3 - p1) / (d1 + d2) + (p3 - p2) / d2) * d1;
+
+    hermite(t, p1, p2, m1, m2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn all_splines() -> Vec<Spline> {
+        vec![
+            Spline::Uniform,
+            Spline::Centripetal,
+            Spline::Chordal,
+            Spline::KochanekBartels {
+                tension: 0.,
+                bias: 0.,
+                continuity: 0.,
+            },
+            Spline::KochanekBartels {
+                tension: 0.5,
+                bias: -0.3,
+                continuity: 0.8,
+            },
+        ]
+    }
+
+    fn points() -> [glm::Vec3; 4] {
+        [
+            glm::vec3(0., 0., 0.),
+            glm::vec3(1., 2., 0.5),
+            glm::vec3(4., 1., -1.),
+            glm::vec3(5., -3., 2.),
+        ]
+    }
+
+    fn assert_close(a: glm::Vec3, b: glm::Vec3) {
+        assert!(glm::distance(&a, &b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn every_spline_goes_through_the_keyframes() {
+        let [p0, p1, p2, p3] = points();
+
+        for spline in all_splines() {
+            assert_close(spline.solve_eq(0., p0, p1, p2, p3), p1);
+            assert_close(spline.solve_eq(1., p0, p1, p2, p3), p2);
+        }
+    }
+
+    #[test]
+    fn uniform_is_kochanek_bartels_without_tension_bias_or_continuity() {
+        let [p0, p1, p2, p3] = points();
+        let kb = Spline::KochanekBartels {
+            tension: 0.,
+            bias: 0.,
+            continuity: 0.,
+        };
+
+        for i in 0..=10 {
+            let t = i as f32 / 10.;
+            assert_close(
+                Spline::Uniform.solve_eq(t, p0, p1, p2, p3),
+                kb.solve_eq(t, p0, p1, p2, p3),
+            );
+        }
+    }
+
+    #[test]
+    fn centripetal_doesnt_overshoot_uneven_keyframes() {
+        // A short segment between two long ones, all on a line.
+        let p0 = glm::vec3(0., 0., 0.);
+        let p1 = glm::vec3(1., 0., 0.);
+        let p2 = glm::vec3(1.1, 0., 0.);
+        let p3 = glm::vec3(10., 0., 0.);
+
+        // How far the segment goes outside of the line between p1 and p2.
+        let overshoot = |spline: Spline| {
+            (0..=100)
+                .map(|i| spline.solve_eq(i as f32 / 100., p0, p1, p2, p3)[0])
+                .map(|x| (p1[0] - x).max(x - p2[0]).max(0.))
+                .fold(0., f32::max)
+        };
+
+        assert!(overshoot(Spline::Uniform) > 0.1);
+        assert!(overshoot(Spline::Centripetal) < EPSILON);
+    }
+}
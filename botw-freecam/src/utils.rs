@@ -1,30 +1,139 @@
+use crate::backend::InputSource;
+use crate::config::{self, Action, Bindings, CameraConfig};
+#[cfg(windows)]
 use crate::globals::*;
 use nalgebra_glm as glm;
+use std::f32::consts::PI;
+#[cfg(windows)]
 use std::ffi::CString;
+#[cfg(windows)]
 use winapi::um::{winuser, xinput};
 
 const DEADZONE: i16 = 10000;
 const MINIMUM_ENGINE_SPEED: f32 = 1e-3;
 
-pub const INSTRUCTIONS: &str = "------------------------------
-USAGE:
-F2 / L2 + Circle / RT + B\t\tActivation
-WASD + Arrow keys / Sticks\t\tCamera movement
-Q - E / R2 - L2 / RT - LT\t\tCamera's height
-F5 - F6 / Up - Down\t\t\tFov control
-PgUp - PgDown / R1 - L1 / RB - LB\tRotation
-F3 - F4 / dpad left - dpad right\tChange movement speed
-Shift / X / A\t\t\t\tAccelerates temporarily
-Tab / Circle / B\t\t\tDecelerate temporarily
-F7\t\t\t\t\tUnlock the character (Locks the camera)
------ Sequence keys -----
-F8\t\t\t\t\tBreaks a current sequence playing
-F9\t\t\t\t\tAdd a point to the sequence
-F10\t\t\t\t\tPlays the sequence
-F11\t\t\t\t\tCleans the sequence
-L\t\t\t\t\tPlays the sequence in a loop (F8 to break it)
-O/P\t\t\t\t\tChange the duration of the sequence
-------------------------------";
+/// Widest fov (in radians) the camera can have.
+pub const MAX_FOV: f32 = 3.12;
+
+/// Longest tick used to move the camera, so a hitch doesn't make it jump.
+pub const MAX_TICK: f32 = 0.1;
+
+/// How fast the duration changes speed up while the keys are held, per second.
+const DOLLY_ACCELERATION: f32 = 1.;
+const DOLLY_INCREMENT: f32 = 1.;
+
+enum Line {
+    Header(&'static str),
+    Actions(&'static [Action], &'static str),
+    /// Keys that can't be rebound.
+    Fixed(&'static str, &'static str),
+}
+
+#[rustfmt::skip]
+const INSTRUCTIONS: &[Line] = &[
+    Line::Header("USAGE:"),
+    Line::Actions(&[Action::Activate], "Activation"),
+    Line::Actions(
+        &[Action::MoveForward, Action::MoveLeft, Action::MoveBackward, Action::MoveRight],
+        "Camera movement (Left stick)",
+    ),
+    Line::Actions(
+        &[Action::LookUp, Action::LookLeft, Action::LookDown, Action::LookRight],
+        "Camera direction (Right stick)",
+    ),
+    Line::Actions(&[Action::Descend, Action::Ascend], "Camera's height (LT - RT)"),
+    Line::Actions(&[Action::FovDecrease, Action::FovIncrease], "Fov control"),
+    Line::Actions(&[Action::RotateLeft, Action::RotateRight], "Rotation"),
+    Line::Actions(&[Action::ResetRoll], "Resets the rotation"),
+    Line::Actions(&[Action::SnapRoll], "Snaps the rotation to the nearest quarter turn"),
+    Line::Actions(&[Action::SpeedDown, Action::SpeedUp], "Change movement speed"),
+    Line::Actions(&[Action::Accelerate], "Accelerates temporarily"),
+    Line::Actions(&[Action::Decelerate], "Decelerate temporarily"),
+    Line::Actions(&[Action::UnlockCharacter], "Unlock the character (Locks the camera)"),
+    Line::Actions(&[Action::ToggleInertia], "Toggles smoothed movement with inertia"),
+    Line::Actions(&[Action::CaptureTarget], "Captures the camera position as the target"),
+    Line::Actions(&[Action::ToggleOrbit], "Orbits around the target (or the focus)"),
+    Line::Actions(&[Action::ToggleLookAt], "Keeps looking at the target while flying"),
+    Line::Actions(&[Action::CycleLeash], "Changes what happens past the safety radius"),
+    Line::Header("----- Sequence keys -----"),
+    Line::Actions(&[Action::AddPoint], "Add a point to the sequence"),
+    Line::Actions(&[Action::Play], "Plays the sequence"),
+    Line::Actions(&[Action::ClearSequence], "Cleans the sequence"),
+    Line::Actions(&[Action::PlayLoop], "Plays the sequence in a loop"),
+    Line::Actions(&[Action::DurationDown, Action::DurationUp], "Change the duration of the sequence"),
+    Line::Actions(
+        &[Action::PointDurationDown, Action::PointDurationUp],
+        "Change the duration of the selected point",
+    ),
+    Line::Actions(&[Action::HoldDown, Action::HoldUp], "Change the hold time of the selected point"),
+    Line::Actions(&[Action::ToggleTiming], "Toggles constant speed along the sequence"),
+    Line::Actions(&[Action::CycleEasing], "Change the easing of the sequence"),
+    Line::Actions(&[Action::CyclePointEasing], "Change the easing of the selected point"),
+    Line::Actions(&[Action::CycleSpline], "Change the kind of spline of the sequence"),
+    Line::Actions(&[Action::ToggleSequenceLookAt], "Makes the sequence look at the target"),
+    Line::Actions(&[Action::SaveSlot], "Hold with 1-9 to save the sequence to a slot"),
+    Line::Actions(&[Action::LoadSlot], "Hold with 1-9 to load the sequence from a slot"),
+    Line::Actions(&[Action::Export], "Exports the sequence to CSV and glTF"),
+    Line::Actions(&[Action::Import], "Imports a sequence from CSV or glTF"),
+    Line::Header("----- Editing points -----"),
+    Line::Actions(&[Action::SelectPrevious, Action::SelectNext], "Selects the previous / next point"),
+    Line::Actions(&[Action::JumpToPoint], "Moves the camera to the selected point"),
+    Line::Actions(&[Action::ReplacePoint], "Replaces the selected point with the current view"),
+    Line::Actions(&[Action::InsertPoint], "Inserts the current view after the selected point"),
+    Line::Actions(&[Action::DeletePoint], "Deletes the selected point"),
+    Line::Actions(
+        &[Action::MovePointEarlier, Action::MovePointLater],
+        "Moves the selected point earlier / later",
+    ),
+    Line::Header("----- While a sequence plays -----"),
+    Line::Actions(&[Action::Pause], "Pauses / resumes the sequence"),
+    Line::Actions(&[Action::ScrubBackward, Action::ScrubForward], "Scrubs the sequence"),
+    Line::Actions(
+        &[Action::PlaybackSlower, Action::PlaybackFaster],
+        "Changes the speed of the sequence",
+    ),
+    Line::Actions(&[Action::Reverse], "Plays the sequence in reverse"),
+    Line::Actions(
+        &[Action::StepBackward, Action::StepForward],
+        "Steps a frame backward / forward while paused",
+    ),
+    Line::Actions(&[Action::SkipBackward, Action::SkipForward], "Jumps to the previous / next point"),
+    Line::Fixed("1-9", "Jumps to a point"),
+    Line::Actions(&[Action::Stop], "Stops the sequence"),
+];
+
+/// Usage instructions with the keys and buttons currently bound.
+pub fn instructions(bindings: &Bindings) -> String {
+    let mut lines = vec!["------------------------------".to_string()];
+
+    for line in INSTRUCTIONS {
+        let (controls, description) = match line {
+            Line::Header(header) => {
+                lines.push(header.to_string());
+                continue;
+            }
+            Line::Fixed(keys, description) => (keys.to_string(), description),
+            Line::Actions(actions, description) => {
+                let (keys, buttons): (Vec<String>, Vec<String>) =
+                    actions.iter().map(|&a| bindings.describe(a)).unzip();
+                let bound = |names: &[String]| names.iter().any(|n| !n.is_empty());
+
+                let controls = match (bound(&keys), bound(&buttons)) {
+                    (false, false) => "(Unbound)".to_string(),
+                    (true, false) => keys.join(" - "),
+                    (false, true) => buttons.join(" - "),
+                    (true, true) => format!("{} / {}", keys.join(" - "), buttons.join(" - ")),
+                };
+                (controls, description)
+            }
+        };
+
+        lines.push(format!("{:<39} {}", controls, description));
+    }
+
+    lines.push("------------------------------".to_string());
+    lines.join("\n")
+}
 
 const CARGO_VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
 const GIT_VERSION: Option<&'static str> = option_env!("GIT_VERSION");
@@ -43,29 +152,68 @@ pub fn get_version() -> String {
 #[rustfmt::skip]
 #[allow(dead_code)]
 pub enum Keys {
+    Key0 = 0x30, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
     A = 0x41, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
 }
 
+#[cfg(windows)]
 pub fn check_key_press(key: i32) -> bool {
     (unsafe { winuser::GetAsyncKeyState(key) } as u32) & 0x8000 != 0
 }
 
+/// Keys held on this tick and on the previous one, so toggles can react only to the moment a key
+/// is pressed instead of sleeping to avoid repeating.
+#[derive(Default, Debug)]
+pub struct KeyPresses {
+    down: Vec<i32>,
+    previous: Vec<i32>,
+}
+
+impl KeyPresses {
+    /// Reads the keys from `source`. Must be called once per tick.
+    pub fn update(&mut self, source: &dyn InputSource) {
+        self.previous = std::mem::take(&mut self.down);
+        self.down = (1..256).filter(|&key| source.key_down(key)).collect();
+    }
+
+    pub fn held(&self, key: i32) -> bool {
+        self.down.contains(&key)
+    }
+
+    pub fn just_pressed(&self, key: i32) -> bool {
+        self.held(key) && !self.previous.contains(&key)
+    }
+}
+
 pub fn calc_eucl_distance(a: &glm::Vec3, b: &glm::Vec3) -> f32 {
     let diff = a - b;
     glm::l2_norm(&diff)
 }
 
+/// State of the controls. The deltas are speeds (per second), which `GameCamera::consume_input`
+/// scales by `dt`.
 #[derive(Default, Debug)]
 pub struct Input {
     pub engine_speed: f32,
+    // Seconds since the last tick
+    pub dt: f32,
     // Deltas with X and Y
     pub delta_pos: (f32, f32),
     pub delta_focus: (f32, f32),
 
-    pub delta_rotation: f32,
+    // Rotation of the camera around the view direction, between -pi and pi
+    pub roll: f32,
+    pub reset_roll: bool,
+    pub snap_roll: bool,
+    // Speeds of the rotation and the fov
+    pub delta_roll: f32,
+    pub delta_fov: f32,
 
     pub delta_altitude: f32,
 
+    // Furthest the camera can look up or down, in radians
+    pub max_pitch: f32,
+
     pub change_active: bool,
     pub is_active: bool,
 
@@ -78,7 +226,34 @@ pub struct Input {
     pub dolly_duration: f32,
     pub dolly_increment: f32,
 
+    // Changes to the duration and hold time of the selected keyframe
+    pub delta_keyframe_duration: f32,
+    pub delta_keyframe_hold: f32,
+
     pub unlock_character: bool,
+
+    pub is_playing: bool,
+    pub playback: PlaybackInput,
+
+    pub bindings: Bindings,
+    pub keys: KeyPresses,
+    pub previous_buttons: u32,
+}
+
+/// Controls of a sequence while it's playing.
+#[derive(Default, Debug)]
+pub struct PlaybackInput {
+    pub stop: bool,
+    pub toggle_pause: bool,
+    pub toggle_reverse: bool,
+    // -1 scrubs backward, 1 forward
+    pub scrub: f32,
+    pub delta_speed: f32,
+    // Frames to step while paused
+    pub step: i32,
+    // Keyframes to skip forward or backward
+    pub skip: i32,
+    pub keyframe: Option<usize>,
 }
 
 impl Input {
@@ -88,7 +263,8 @@ impl Input {
             engine_speed: MINIMUM_ENGINE_SPEED,
             speed_multiplier: 1.,
             dolly_duration: 10.,
-            dolly_increment: 0.01,
+            dolly_increment: DOLLY_INCREMENT,
+            max_pitch: CameraConfig::default().max_pitch.to_radians(),
             ..Input::default()
         }
     }
@@ -97,6 +273,13 @@ impl Input {
         self.delta_pos = (0., 0.);
         self.delta_focus = (0., 0.);
         self.delta_altitude = 0.;
+        self.delta_roll = 0.;
+        self.delta_fov = 0.;
+        self.reset_roll = false;
+        self.snap_roll = false;
+        self.delta_keyframe_duration = 0.;
+        self.delta_keyframe_hold = 0.;
+        self.playback = PlaybackInput::default();
         self.change_active = false;
 
         #[cfg(debug_assertions)]
@@ -105,14 +288,26 @@ impl Input {
         }
     }
 
+    /// Whether any of the keys bound to `action` is being held.
+    pub fn held(&self, action: Action) -> bool {
+        self.bindings.held(action, &self.keys)
+    }
+
+    /// Whether any of the keys bound to `action` was pressed since the last tick.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.bindings.just_pressed(action, &self.keys)
+    }
+
     pub fn sanitize(&mut self) {
         if self.fov < 1e-3 {
             self.fov = 0.01;
         }
-        if self.fov > 3.12 {
-            self.fov = 3.12;
+        if self.fov > MAX_FOV {
+            self.fov = MAX_FOV;
         }
 
+        self.roll = (self.roll + PI).rem_euclid(2. * PI) - PI;
+
         if self.dolly_duration < 0.1 {
             self.dolly_duration = 0.1;
         }
@@ -131,18 +326,20 @@ impl Input {
     }
 }
 
-pub fn handle_keyboard(input: &mut Input) {
+pub fn handle_keyboard(input: &mut Input, source: &dyn InputSource) {
+    input.keys.update(source);
+
     macro_rules! handle_state {
             ([ $key_pos:expr, $key_neg:expr, $var:ident, $val:expr ]; $($tt:tt)*) => {
                 handle_state!([$key_pos, $key_neg, $var = $val, $var = - $val]; $($tt)*);
             };
 
             ([ $key_pos:expr, $key_neg:expr, $pos_do:expr, $neg_do:expr ]; $($tt:tt)*) => {
-                if (winuser::GetAsyncKeyState($key_pos as i32) as u32 & 0x8000) != 0 {
+                if input.held($key_pos) {
                     $pos_do;
                 }
 
-                if (winuser::GetAsyncKeyState($key_neg as i32) as u32 & 0x8000) != 0 {
+                if input.held($key_neg) {
                     $neg_do;
                 }
                 handle_state!($($tt)*);
@@ -151,57 +348,79 @@ pub fn handle_keyboard(input: &mut Input) {
             () => {}
         }
 
-    unsafe {
-        handle_state! {
-                // Others
-                [winuser::VK_F2, winuser::VK_F3, input.change_active = true, input.change_active = false];
-        }
+    if input.held(Action::Activate) {
+        input.change_active = true;
     }
 
     if !input.is_active {
         return;
     }
 
-    unsafe {
-        handle_state! {
-            // Position of the camer
-            [Keys::W, Keys::S, input.delta_pos.1 = 0.02, input.delta_pos.1 = -0.02];
-            [Keys::A, Keys::D, input.delta_pos.0 = 0.02, input.delta_pos.0 = -0.02];
-            [winuser::VK_UP, winuser::VK_DOWN, input.delta_focus.1 = -0.02, input.delta_focus.1 = 0.02];
-            [winuser::VK_LEFT, winuser::VK_RIGHT, input.delta_focus.0 = -0.02, input.delta_focus.0 = 0.02];
+    if input.is_playing {
+        handle_playback_keyboard(input);
+        return;
+    }
 
-            [Keys::Q, Keys::E, input.delta_altitude -= 0.02, input.delta_altitude += 0.02];
+    let dt = input.dt;
 
-            // Rotation
-            [winuser::VK_NEXT, winuser::VK_PRIOR, input.delta_rotation += 0.02, input.delta_rotation -= 0.02];
+    // Speeds per second
+    handle_state! {
+        // Position of the camer
+        [Action::MoveForward, Action::MoveBackward, input.delta_pos.1 = 2., input.delta_pos.1 = -2.];
+        [Action::MoveLeft, Action::MoveRight, input.delta_pos.0 = 2., input.delta_pos.0 = -2.];
+        [Action::LookUp, Action::LookDown, input.delta_focus.1 = -2., input.delta_focus.1 = 2.];
+        [Action::LookLeft, Action::LookRight, input.delta_focus.0 = -2., input.delta_focus.0 = 2.];
 
-            //  FoV
-            [winuser::VK_F5, winuser::VK_F6, input.fov -= 0.02, input.fov += 0.02];
+        [Action::Descend, Action::Ascend, input.delta_altitude -= 2., input.delta_altitude += 2.];
 
-            [winuser::VK_F3, winuser::VK_F4, input.speed_multiplier -= 0.01, input.speed_multiplier += 0.01];
+        // Rotation
+        [Action::RotateRight, Action::RotateLeft, input.delta_roll += 2., input.delta_roll -= 2.];
 
-        }
+        //  FoV
+        [Action::FovDecrease, Action::FovIncrease, input.delta_fov -= 2., input.delta_fov += 2.];
+
+        [Action::SpeedDown, Action::SpeedUp, input.speed_multiplier -= dt, input.speed_multiplier += dt];
     }
 
-    if check_key_press(Keys::P as _) {
-        input.dolly_duration += input.dolly_increment;
-        input.dolly_increment *= 1.01;
+    if input.just_pressed(Action::SnapRoll) {
+        input.snap_roll = true;
+    } else if input.just_pressed(Action::ResetRoll) {
+        input.reset_roll = true;
+    }
+
+    let increment = input.dolly_increment * dt;
+    let mut changing_duration = true;
+    if input.held(Action::PointDurationUp) {
+        input.delta_keyframe_duration = increment;
+    } else if input.held(Action::PointDurationDown) {
+        input.delta_keyframe_duration = -increment;
+    } else if input.held(Action::DurationUp) {
+        input.dolly_duration += increment;
         println!("Duration: {}", input.dolly_duration);
-    } else if check_key_press(Keys::O as _) {
-        input.dolly_duration -= input.dolly_increment;
-        input.dolly_increment *= 1.01;
+    } else if input.held(Action::DurationDown) {
+        input.dolly_duration -= increment;
         println!("Duration: {}", input.dolly_duration);
+    } else if input.held(Action::HoldUp) {
+        input.delta_keyframe_hold = increment;
+    } else if input.held(Action::HoldDown) {
+        input.delta_keyframe_hold = -increment;
     } else {
-        input.dolly_increment = 0.01
+        changing_duration = false;
     }
 
-    if check_key_press(winuser::VK_LSHIFT) {
+    input.dolly_increment = if changing_duration {
+        input.dolly_increment * (DOLLY_ACCELERATION * dt).exp()
+    } else {
+        DOLLY_INCREMENT
+    };
+
+    if input.held(Action::Accelerate) {
         input.delta_pos.0 *= 8.;
         input.delta_pos.1 *= 8.;
         input.delta_altitude *= 8.;
     }
 
-    if check_key_press(winuser::VK_TAB) {
+    if input.held(Action::Decelerate) {
         input.delta_pos.0 *= 0.2;
         input.delta_pos.1 *= 0.2;
         input.delta_altitude *= 0.2;
@@ -212,6 +431,45 @@ pub fn handle_keyboard(input: &mut Input) {
     input.delta_altitude *= input.speed_multiplier;
 }
 
+fn handle_playback_keyboard(input: &mut Input) {
+    // The controller was already read, so its presses are kept.
+    input.playback.stop |= input.just_pressed(Action::Stop);
+    input.playback.toggle_pause |= input.just_pressed(Action::Pause);
+    input.playback.toggle_reverse |= input.just_pressed(Action::Reverse);
+
+    if input.held(Action::ScrubForward) {
+        input.playback.scrub = 1.;
+    } else if input.held(Action::ScrubBackward) {
+        input.playback.scrub = -1.;
+    }
+
+    if input.held(Action::PlaybackFaster) {
+        input.playback.delta_speed = 1.;
+    } else if input.held(Action::PlaybackSlower) {
+        input.playback.delta_speed = -1.;
+    }
+
+    // Steps are only used while paused, otherwise the arrows scrub
+    if input.just_pressed(Action::StepForward) {
+        input.playback.step = 1;
+    } else if input.just_pressed(Action::StepBackward) {
+        input.playback.step = -1;
+    }
+
+    if input.just_pressed(Action::SkipForward) {
+        input.playback.skip = 1;
+    } else if input.just_pressed(Action::SkipBackward) {
+        input.playback.skip = -1;
+    }
+
+    for keyframe in 1..=9 {
+        if input.keys.just_pressed(Keys::Key0 as i32 + keyframe as i32) {
+            input.playback.keyframe = Some(keyframe - 1);
+        }
+    }
+}
+
+#[cfg(windows)]
 pub fn error_message(message: &str) {
     let title = CString::new("Error while patching").unwrap();
     let message = CString::new(message).unwrap();
@@ -226,14 +484,18 @@ pub fn error_message(message: &str) {
     }
 }
 
-pub fn handle_controller(input: &mut Input, func: fn(u32, &mut xinput::XINPUT_STATE) -> u32) {
-    let mut xs: xinput::XINPUT_STATE = unsafe { std::mem::zeroed() };
-    func(0, &mut xs);
+pub fn handle_controller(input: &mut Input, source: &dyn InputSource) {
+    let gp = source.gamepad();
+    let state = config::controller_state(&gp);
+    let previous = input.previous_buttons;
+    input.previous_buttons = state;
 
-    let gp = xs.Gamepad;
+    let bindings = &input.bindings;
+    let held = |action| bindings.button_held(action, state);
+    let just_pressed = |action| bindings.button_just_pressed(action, state, previous);
 
     // check camera activation
-    if gp.bLeftTrigger > 150 && ((gp.wButtons & 0x2000) == 0x2000) {
+    if held(Action::Activate) {
         input.change_active = true;
     }
 
@@ -242,36 +504,73 @@ pub fn handle_controller(input: &mut Input, func: fn(u32, &mut xinput::XINPUT_ST
         return;
     }
 
+    if input.is_playing {
+        let playback = &mut input.playback;
+        playback.toggle_pause |= just_pressed(Action::Pause);
+        playback.stop |= just_pressed(Action::Stop);
+        playback.toggle_reverse |= just_pressed(Action::Reverse);
+
+        // Steps are only used while paused, otherwise the dpad scrubs
+        if held(Action::ScrubForward) {
+            playback.scrub = 1.;
+        } else if held(Action::ScrubBackward) {
+            playback.scrub = -1.;
+        }
+        if just_pressed(Action::StepForward) {
+            playback.step = 1;
+        } else if just_pressed(Action::StepBackward) {
+            playback.step = -1;
+        }
+
+        if held(Action::PlaybackFaster) {
+            playback.delta_speed = 1.;
+        } else if held(Action::PlaybackSlower) {
+            playback.delta_speed = -1.;
+        }
+
+        if just_pressed(Action::SkipForward) {
+            playback.skip = 1;
+        } else if just_pressed(Action::SkipBackward) {
+            playback.skip = -1;
+        }
+
+        return;
+    }
+
+    let dt = input.dt;
+
     // modify speed
-    if (gp.wButtons & 0x4) != 0 {
-        input.speed_multiplier -= 0.01;
+    if held(Action::SpeedDown) {
+        input.speed_multiplier -= dt;
     }
-    if (gp.wButtons & 0x8) != 0 {
-        input.speed_multiplier += 0.01;
+    if held(Action::SpeedUp) {
+        input.speed_multiplier += dt;
     }
 
-    if (gp.wButtons & (0x200)) != 0 {
-        input.delta_rotation += 0.01;
+    if held(Action::RotateRight) {
+        input.delta_roll += 1.;
     }
 
-    if (gp.wButtons & (0x100)) != 0 {
-        input.delta_rotation -= 0.01;
+    if held(Action::RotateLeft) {
+        input.delta_roll -= 1.;
     }
 
-    if (gp.wButtons & (0x200 | 0x100)) == (0x200 | 0x100) {
-        input.delta_rotation = 0.;
+    if just_pressed(Action::SnapRoll) {
+        input.snap_roll = true;
+    } else if just_pressed(Action::ResetRoll) {
+        input.reset_roll = true;
     }
 
-    if (gp.wButtons & 0x1) != 0 {
-        input.fov -= 0.01;
+    if held(Action::FovDecrease) {
+        input.delta_fov -= 1.;
     }
 
-    if (gp.wButtons & 0x2) != 0 {
-        input.fov += 0.01;
+    if held(Action::FovIncrease) {
+        input.delta_fov += 1.;
     }
 
-    input.delta_altitude += -(gp.bLeftTrigger as f32) / 5e3;
-    input.delta_altitude += (gp.bRightTrigger as f32) / 5e3;
+    input.delta_altitude += -(gp.left_trigger as f32) / 50.;
+    input.delta_altitude += (gp.right_trigger as f32) / 50.;
 
     macro_rules! dead_zone {
         ($val:expr) => {
@@ -284,28 +583,29 @@ pub fn handle_controller(input: &mut Input, func: fn(u32, &mut xinput::XINPUT_ST
     }
 
     input.delta_pos.0 =
-        -(dead_zone!(gp.sThumbLX) as f32) / ((i16::MAX as f32) * 1e2) * input.speed_multiplier;
+        -(dead_zone!(gp.left_stick.0) as f32) / (i16::MAX as f32) * input.speed_multiplier;
     input.delta_pos.1 =
-        (dead_zone!(gp.sThumbLY) as f32) / ((i16::MAX as f32) * 1e2) * input.speed_multiplier;
+        (dead_zone!(gp.left_stick.1) as f32) / (i16::MAX as f32) * input.speed_multiplier;
 
-    input.delta_focus.0 = (dead_zone!(gp.sThumbRX) as f32) / ((i16::MAX as f32) * 4e1);
-    input.delta_focus.1 = -(dead_zone!(gp.sThumbRY) as f32) / ((i16::MAX as f32) * 4e1);
+    input.delta_focus.0 = (dead_zone!(gp.right_stick.0) as f32) / (i16::MAX as f32) * 2.5;
+    input.delta_focus.1 = -(dead_zone!(gp.right_stick.1) as f32) / (i16::MAX as f32) * 2.5;
 
     input.delta_altitude *= input.speed_multiplier;
 
-    if gp.wButtons & 0x1000 != 0 {
+    if held(Action::Accelerate) {
         input.delta_pos.0 *= 8.;
         input.delta_pos.1 *= 8.;
         input.delta_altitude *= 8.;
     }
 
-    if gp.wButtons & 0x4000 != 0 {
+    if held(Action::Decelerate) {
         input.delta_pos.0 *= 0.2;
         input.delta_pos.1 *= 0.2;
         input.delta_altitude *= 0.2;
     }
 }
 
+#[cfg(windows)]
 #[no_mangle]
 pub unsafe extern "system" fn dummy_xinput(a: u32, b: &mut xinput::XINPUT_STATE) -> u32 {
     if g_camera_active != 0 {
@@ -315,3 +615,142 @@ pub unsafe extern "system" fn dummy_xinput(a: u32, b: &mut xinput::XINPUT_STATE)
 
     xinput::XInputGetState(a, b)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{buttons, vk, Frame, Gamepad, ScriptedInput};
+
+    const DT: f32 = 0.01;
+
+    fn active_input() -> Input {
+        let mut input = Input::new();
+        input.is_active = true;
+        input.dt = DT;
+        input
+    }
+
+    /// Runs both handlers on every frame, like the main loop does, and returns the input of the
+    /// last one.
+    fn run(frames: Vec<Frame>) -> Input {
+        let ticks = frames.len();
+        let mut source = ScriptedInput::new(frames);
+        let mut input = active_input();
+
+        for tick in 0..ticks {
+            if tick > 0 {
+                input.reset();
+                source.advance();
+            }
+            handle_controller(&mut input, &source);
+            handle_keyboard(&mut input, &source);
+        }
+
+        input
+    }
+
+    fn keys(keys: &[i32]) -> Frame {
+        Frame {
+            keys: keys.to_vec(),
+            ..Frame::default()
+        }
+    }
+
+    fn gamepad(gamepad: Gamepad) -> Frame {
+        Frame {
+            gamepad,
+            ..Frame::default()
+        }
+    }
+
+    fn assert_close(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn sticks_inside_the_deadzone_are_ignored() {
+        let input = run(vec![gamepad(Gamepad {
+            left_stick: (DEADZONE - 1, -(DEADZONE - 1)),
+            right_stick: (-(DEADZONE - 1), DEADZONE - 1),
+            ..Gamepad::default()
+        })]);
+        assert_eq!(input.delta_pos, (0., 0.));
+        assert_eq!(input.delta_focus, (0., 0.));
+
+        let input = run(vec![gamepad(Gamepad {
+            left_stick: (i16::MAX, DEADZONE),
+            right_stick: (0, i16::MAX),
+            ..Gamepad::default()
+        })]);
+        assert_close(input.delta_pos, (-1., DEADZONE as f32 / i16::MAX as f32));
+        assert_close(input.delta_focus, (0., -2.5));
+    }
+
+    #[test]
+    fn accelerate_and_decelerate_scale_the_movement() {
+        let forward = Keys::W as i32;
+
+        assert_close(run(vec![keys(&[forward])]).delta_pos, (0., 2.));
+        assert_close(run(vec![keys(&[forward, vk::LSHIFT])]).delta_pos, (0., 16.));
+        assert_close(run(vec![keys(&[forward, vk::TAB])]).delta_pos, (0., 0.4));
+
+        let stick = |buttons| Gamepad {
+            buttons,
+            left_stick: (0, i16::MAX),
+            ..Gamepad::default()
+        };
+        assert_close(run(vec![gamepad(stick(0))]).delta_pos, (0., 1.));
+        assert_close(run(vec![gamepad(stick(buttons::A))]).delta_pos, (0., 8.));
+        assert_close(run(vec![gamepad(stick(buttons::X))]).delta_pos, (0., 0.2));
+    }
+
+    #[test]
+    fn controller_controls_the_playback_along_with_the_keyboard() {
+        let playing = |frame| {
+            let source = ScriptedInput::new(vec![frame]);
+            let mut input = active_input();
+            input.is_playing = true;
+            handle_controller(&mut input, &source);
+            handle_keyboard(&mut input, &source);
+            input.playback
+        };
+
+        let pressed = |buttons| {
+            playing(gamepad(Gamepad {
+                buttons,
+                ..Gamepad::default()
+            }))
+        };
+        assert!(pressed(buttons::B).stop);
+        assert!(pressed(buttons::A).toggle_pause);
+        assert!(pressed(buttons::X).toggle_reverse);
+
+        let playback = playing(keys(&[vk::SPACE, Keys::R as i32]));
+        assert!(playback.toggle_pause && playback.toggle_reverse && !playback.stop);
+    }
+
+    #[test]
+    fn speed_multiplier_changes_with_the_time_held() {
+        // One second holding speed up, then moving forward.
+        let mut frames = vec![keys(&[vk::F1 + 3]); 100];
+        frames.push(keys(&[Keys::W as i32]));
+        let input = run(frames);
+
+        assert!((input.speed_multiplier - 2.).abs() < 1e-3);
+        assert_close(input.delta_pos, (0., 4.));
+
+        let frames = vec![
+            gamepad(Gamepad {
+                buttons: buttons::DPAD_LEFT,
+                ..Gamepad::default()
+            });
+            50
+        ];
+        assert!((run(frames).speed_multiplier - 0.5).abs() < 1e-3);
+    }
+}